@@ -1,4 +1,4 @@
-use std::{env, str::FromStr};
+use std::{env, str::FromStr, time::Duration};
 
 use crate::{data, diagnostics, error::Error, server};
 
@@ -30,6 +30,204 @@ impl Config {
             config.diagnostics.min_level = diagnostics::Level::Debug;
         }
 
+        let parse_mode_var = if is_seq_app {
+            "SEQ_APP_SETTING_PARSEMODE"
+        } else {
+            "SYSLOG_PARSE_MODE"
+        };
+        read_environment(&mut config.data.parse_mode, parse_mode_var)?;
+
+        let parse_rfc3164_tags_var = if is_seq_app {
+            "SEQ_APP_SETTING_PARSERFC3164TAGS"
+        } else {
+            "SYSLOG_PARSE_RFC3164_TAGS"
+        };
+        config.data.parse_rfc3164_tags = is_truthy(parse_rfc3164_tags_var)?;
+
+        let charsets_var = if is_seq_app {
+            "SEQ_APP_SETTING_FALLBACKCHARSETS"
+        } else {
+            "SYSLOG_FALLBACK_CHARSETS"
+        };
+        read_environment(&mut config.data.charsets, charsets_var)?;
+
+        let structured_data_format_var = if is_seq_app {
+            "SEQ_APP_SETTING_STRUCTUREDDATAFORMAT"
+        } else {
+            "SYSLOG_STRUCTURED_DATA_FORMAT"
+        };
+        read_environment(&mut config.data.structured_data_format, structured_data_format_var)?;
+
+        let split_sd_enterprise_ids_var = if is_seq_app {
+            "SEQ_APP_SETTING_SPLITSDENTERPRISEIDS"
+        } else {
+            "SYSLOG_SPLIT_SD_ENTERPRISE_IDS"
+        };
+        config.data.split_sd_enterprise_ids = is_truthy(split_sd_enterprise_ids_var)?;
+
+        let infer_sd_param_types_var = if is_seq_app {
+            "SEQ_APP_SETTING_INFERSDPARAMTYPES"
+        } else {
+            "SYSLOG_INFER_SD_PARAM_TYPES"
+        };
+        config.data.infer_sd_param_types = is_truthy(infer_sd_param_types_var)?;
+
+        let decode_registered_sd_ids_var = if is_seq_app {
+            "SEQ_APP_SETTING_DECODEREGISTEREDSDIDS"
+        } else {
+            "SYSLOG_DECODE_REGISTERED_SD_IDS"
+        };
+        config.data.decode_registered_sd_ids = is_truthy(decode_registered_sd_ids_var)?;
+
+        let original_timestamps_var = if is_seq_app {
+            "SEQ_APP_SETTING_ORIGINALTIMESTAMPS"
+        } else {
+            "SYSLOG_ORIGINAL_TIMESTAMPS"
+        };
+        config.data.original_timestamps = is_truthy(original_timestamps_var)?;
+
+        let receive_time_var = if is_seq_app {
+            "SEQ_APP_SETTING_RECEIVETIME"
+        } else {
+            "SYSLOG_RECEIVE_TIME"
+        };
+        config.data.receive_time = is_truthy(receive_time_var)?;
+
+        let timestamp_policy_var = if is_seq_app {
+            "SEQ_APP_SETTING_TIMESTAMPPOLICY"
+        } else {
+            "SYSLOG_TIMESTAMP_POLICY"
+        };
+        read_environment(&mut config.data.timestamp_policy, timestamp_policy_var)?;
+
+        let profiles_var = if is_seq_app {
+            "SEQ_APP_SETTING_PROFILES"
+        } else {
+            "SYSLOG_PROFILES"
+        };
+        read_environment(&mut config.data.profiles, profiles_var)?;
+
+        let key_value_var = if is_seq_app {
+            "SEQ_APP_SETTING_KEYVALUESOURCES"
+        } else {
+            "SYSLOG_KEY_VALUE_SOURCES"
+        };
+        read_environment(&mut config.data.key_value, key_value_var)?;
+
+        let parse_rules_var = if is_seq_app {
+            "SEQ_APP_SETTING_PARSERULES"
+        } else {
+            "SYSLOG_PARSE_RULES"
+        };
+        read_environment(&mut config.data.parse_rules, parse_rules_var)?;
+
+        let level_mapping_var = if is_seq_app {
+            "SEQ_APP_SETTING_LEVELMAPPING"
+        } else {
+            "SYSLOG_LEVEL_MAPPING"
+        };
+        read_environment(&mut config.data.level_mapping, level_mapping_var)?;
+
+        let event_types_var = if is_seq_app {
+            "SEQ_APP_SETTING_EVENTTYPES"
+        } else {
+            "SYSLOG_EVENT_TYPES"
+        };
+        config.data.event_types = is_truthy(event_types_var)?;
+
+        let masked_templates_var = if is_seq_app {
+            "SEQ_APP_SETTING_MASKEDTEMPLATES"
+        } else {
+            "SYSLOG_MASKED_TEMPLATES"
+        };
+        config.data.masked_templates = is_truthy(masked_templates_var)?;
+
+        let escapes_var = if is_seq_app {
+            "SEQ_APP_SETTING_DECODEESCAPES"
+        } else {
+            "SYSLOG_DECODE_ESCAPES"
+        };
+        read_environment(&mut config.data.escapes, escapes_var)?;
+
+        let detect_exceptions_var = if is_seq_app {
+            "SEQ_APP_SETTING_DETECTEXCEPTIONS"
+        } else {
+            "SYSLOG_DETECT_EXCEPTIONS"
+        };
+        config.data.detect_exceptions = is_truthy(detect_exceptions_var)?;
+
+        let static_properties_var = if is_seq_app {
+            "SEQ_APP_SETTING_STATICPROPERTIES"
+        } else {
+            "SYSLOG_STATIC_PROPERTIES"
+        };
+        read_environment(&mut config.data.static_properties, static_properties_var)?;
+
+        let rename_fields_var = if is_seq_app {
+            "SEQ_APP_SETTING_RENAMEFIELDS"
+        } else {
+            "SYSLOG_RENAME_FIELDS"
+        };
+        read_environment(&mut config.data.rename_fields, rename_fields_var)?;
+
+        let drop_fields_var = if is_seq_app {
+            "SEQ_APP_SETTING_DROPFIELDS"
+        } else {
+            "SYSLOG_DROP_FIELDS"
+        };
+        read_environment(&mut config.data.drop_fields, drop_fields_var)?;
+
+        let filter_rules_var = if is_seq_app {
+            "SEQ_APP_SETTING_FILTERRULES"
+        } else {
+            "SYSLOG_FILTER_RULES"
+        };
+        read_environment(&mut config.data.filter_rules, filter_rules_var)?;
+
+        let redaction_rules_var = if is_seq_app {
+            "SEQ_APP_SETTING_REDACTIONRULES"
+        } else {
+            "SYSLOG_REDACTION_RULES"
+        };
+        read_environment(&mut config.data.redaction_rules, redaction_rules_var)?;
+
+        let redaction_key_var = if is_seq_app {
+            "SEQ_APP_SETTING_REDACTIONKEY"
+        } else {
+            "SYSLOG_REDACTION_KEY"
+        };
+        read_environment(&mut config.data.redaction_key, redaction_key_var)?;
+
+        let split_messages_var = if is_seq_app {
+            "SEQ_APP_SETTING_SPLITMESSAGES"
+        } else {
+            "SYSLOG_SPLIT_MESSAGES"
+        };
+        config.data.split_messages = is_truthy(split_messages_var)?;
+
+        let multiline_start_patterns_var = if is_seq_app {
+            "SEQ_APP_SETTING_MULTILINESTARTPATTERNS"
+        } else {
+            "SYSLOG_MULTILINE_START_PATTERNS"
+        };
+        let mut multiline_start_patterns = String::new();
+        read_environment(&mut multiline_start_patterns, multiline_start_patterns_var)?;
+        config.data.reassembly.start_patterns = multiline_start_patterns
+            .lines()
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(str::to_owned)
+            .collect();
+
+        let multiline_timeout_var = if is_seq_app {
+            "SEQ_APP_SETTING_MULTILINETIMEOUTMS"
+        } else {
+            "SYSLOG_MULTILINE_TIMEOUT_MS"
+        };
+        let mut multiline_timeout_ms = config.data.reassembly.flush_timeout.as_millis() as u64;
+        read_environment(&mut multiline_timeout_ms, multiline_timeout_var)?;
+        config.data.reassembly.flush_timeout = Duration::from_millis(multiline_timeout_ms);
+
         Ok(config)
     }
 }
@@ -41,10 +239,10 @@ pub fn is_seq_app() -> bool {
 fn is_truthy(name: impl AsRef<str>) -> Result<bool, Error> {
     match env::var(name.as_ref()) {
         // The evironment variable contains a truthy value
-        Ok(ref v) if v == "True" || v == "true" => return Ok(true),
+        Ok(ref v) if v == "True" || v == "true" => Ok(true),
         // The environment variable is not set or doesn't contain
         // a truthy value
-        Ok(_) | Err(env::VarError::NotPresent) => return Ok(false),
+        Ok(_) | Err(env::VarError::NotPresent) => Ok(false),
         // The environment variable is invalid
         Err(e) => Err(e)?,
     }
@@ -57,9 +255,9 @@ where
 {
     match env::var(name.as_ref()) {
         // The environment variable exists, but is empty
-        Ok(ref v) if v == "" => return Ok(()),
+        Ok(ref v) if v.is_empty() => Ok(()),
         // The environment variable does not exist
-        Err(env::VarError::NotPresent) => return Ok(()),
+        Err(env::VarError::NotPresent) => Ok(()),
         // The environment variable is invalid
         Err(e) => Err(e)?,
         // The environment variable has a value
@@ -0,0 +1,93 @@
+use std::{borrow::Cow, str::FromStr};
+
+use encoding_rs::Encoding;
+
+use crate::{
+    data::selector::Selector,
+    error::{err_msg, Error},
+};
+
+/**
+The character sets to decode message text that isn't valid UTF-8 with.
+
+Charsets are separated by `;`, and are either a label like `windows-1252` that applies
+to all messages, or a label followed by a selector in brackets, like
+`shift_jis [hostname=jp-*]`. The first charset whose selector matches a message is used.
+Text that isn't valid UTF-8 and doesn't have a charset is decoded with replacement characters.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct Charsets(Vec<(Selector, &'static Encoding)>);
+
+impl Charsets {
+    /**
+    Decode message text, falling back to the charset for its app name and hostname if it isn't valid UTF-8.
+    */
+    pub(super) fn decode<'a>(&self, text: &'a [u8], app_name: Option<&str>, hostname: Option<&str>) -> Cow<'a, str> {
+        if let Ok(text) = std::str::from_utf8(text) {
+            return Cow::Borrowed(text);
+        }
+
+        match self.0.iter().find(|(selector, _)| selector.matches(app_name, hostname)) {
+            Some((_, encoding)) => encoding.decode_without_bom_handling(text).0,
+            None => String::from_utf8_lossy(text),
+        }
+    }
+}
+
+impl FromStr for Charsets {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(';')
+            .map(str::trim)
+            .filter(|charset| !charset.is_empty())
+            .map(|charset| {
+                let (label, selector) = match charset.find('[') {
+                    Some(open) => {
+                        let selector = charset[open + 1..]
+                            .trim_end()
+                            .strip_suffix(']')
+                            .ok_or_else(|| err_msg(format!("invalid charset `{}`, expected a closing `]`", charset)))?;
+
+                        (charset[..open].trim(), selector.parse()?)
+                    }
+                    None => (charset, Selector::any()),
+                };
+
+                let encoding = Encoding::for_label(label.as_bytes())
+                    .ok_or_else(|| err_msg(format!("unrecognized charset `{}`", label)))?;
+
+                Ok((selector, encoding))
+            })
+            .collect::<Result<_, Error>>()
+            .map(Charsets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_with_fallback_charset() {
+        let charsets: Charsets = "shift_jis [hostname=jp-*]; windows-1252".parse().unwrap();
+
+        assert_eq!("caf\u{e9}", charsets.decode(b"caf\xe9", None, Some("fr-01")));
+        assert_eq!("\u{65e5}\u{672c}", charsets.decode(b"\x93\xfa\x96\x7b", None, Some("jp-01")));
+        assert_eq!("caf\u{e9}", charsets.decode("caf\u{e9}".as_bytes(), None, Some("jp-01")));
+    }
+
+    #[test]
+    fn decodes_lossily_without_charset() {
+        let charsets = Charsets::default();
+
+        assert_eq!("caf\u{fffd}", charsets.decode(b"caf\xe9", None, None));
+    }
+
+    #[test]
+    fn invalid_charsets_are_rejected() {
+        assert!("not-a-charset".parse::<Charsets>().is_err());
+        assert!("latin1 [hostname=fr-*".parse::<Charsets>().is_err());
+        assert!("latin1 [host=fr-*]".parse::<Charsets>().is_err());
+    }
+}
@@ -1,31 +1,92 @@
 use std::{borrow::Cow, collections::HashMap};
+use serde::{Deserializer, Serializer};
 use serde_json::Value;
 use chrono::{DateTime, Utc};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Message<'a> {
     #[serde(rename = "@t")]
-    pub timestamp: DateTime<Utc>,
+    pub timestamp: Timestamp,
 
     #[serde(rename = "@l")]
-    pub level: Option<&'a str>,
+    pub level: Option<Cow<'a, str>>,
 
     #[serde(rename = "@m")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub message: Option<Cow<'a, str>>,
 
-    // @mt and @x are currently not used
     #[serde(rename = "@mt")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub message_template: Option<&'a str>,
+    pub message_template: Option<Cow<'a, str>>,
 
     #[serde(rename = "@x")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub exception: Option<&'a str>,
+    pub exception: Option<Cow<'a, str>>,
 
-    // @i and @r are currently not implemented
+    #[serde(rename = "@i")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_type: Option<String>,
+
+    // @r is currently not implemented
 
     // Everything else
     #[serde(flatten)]
-    pub additional: HashMap<&'a str, Value>,
+    pub additional: HashMap<Cow<'a, str>, Value>,
+}
+
+impl<'a> Message<'a> {
+    /**
+    Take ownership of any borrowed text, so the message can outlive the input it was parsed from.
+    */
+    pub fn into_owned(self) -> Message<'static> {
+        Message {
+            timestamp: self.timestamp,
+            level: self.level.map(|level| Cow::Owned(level.into_owned())),
+            message: self.message.map(|message| Cow::Owned(message.into_owned())),
+            message_template: self.message_template.map(|template| Cow::Owned(template.into_owned())),
+            exception: self.exception.map(|exception| Cow::Owned(exception.into_owned())),
+            event_type: self.event_type,
+            additional: self.additional.into_iter().map(|(k, v)| (Cow::Owned(k.into_owned()), v)).collect(),
+        }
+    }
+}
+
+/**
+A timestamp, serialized with as many fractional second digits as it was received with.
+
+Timestamps without a known precision use as many digits as they need.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timestamp {
+    pub value: DateTime<Utc>,
+    pub fraction_digits: Option<usize>,
+}
+
+impl From<DateTime<Utc>> for Timestamp {
+    fn from(value: DateTime<Utc>) -> Self {
+        Timestamp {
+            value,
+            fraction_digits: None,
+        }
+    }
+}
+
+impl serde::Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.fraction_digits {
+            Some(0) => serializer.collect_str(&self.value.format("%Y-%m-%dT%H:%M:%SZ")),
+            Some(digits) => {
+                let nanos = format!("{:09}", self.value.timestamp_subsec_nanos());
+
+                serializer.collect_str(&format_args!("{}.{}Z", self.value.format("%Y-%m-%dT%H:%M:%S"), &nanos[..digits.min(9)]))
+            }
+            None => serde::Serialize::serialize(&self.value, serializer),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <DateTime<Utc> as serde::Deserialize>::deserialize(deserializer).map(Timestamp::from)
+    }
 }
@@ -0,0 +1,110 @@
+// Control characters escaped by relays before messages reach us.
+//
+// rsyslog's `EscapeControlCharactersOnReceive` replaces control characters with `#` and their
+// three-digit octal value, so a tab becomes `#011` and a newline `#012`:
+//
+//   Request failed#012java.lang.IllegalStateException: closed#012#011at com.example.Main.main(Main.java:7)
+//
+// Other relays use backslash escapes, like `\n`, `\r`, `\t`, and `\\`.
+
+use super::Escapes;
+
+/**
+Restore the escaped control characters in a message.
+
+If the message doesn't contain any escapes then `None` is returned.
+*/
+pub(super) fn decode(message: &str, escapes: Escapes) -> Option<String> {
+    let mut decoded = String::new();
+    let mut unescaped_from = 0;
+
+    let mut i = 0;
+    let bytes = message.as_bytes();
+    while i < bytes.len() {
+        let escape = match bytes[i] {
+            b'#' if escapes.rsyslog => octal(&bytes[i + 1..]).map(|c| (c, 4)),
+            b'\\' if escapes.backslash => backslash(&bytes[i + 1..]).map(|c| (c, 2)),
+            _ => None,
+        };
+
+        match escape {
+            Some((c, len)) => {
+                decoded.push_str(&message[unescaped_from..i]);
+                decoded.push(c);
+
+                i += len;
+                unescaped_from = i;
+            }
+            None => i += 1,
+        }
+    }
+
+    if unescaped_from == 0 {
+        return None;
+    }
+
+    decoded.push_str(&message[unescaped_from..]);
+    Some(decoded)
+}
+
+// `011`, where only control characters are escaped
+fn octal(i: &[u8]) -> Option<char> {
+    let digits = i.get(..3)?;
+    if !digits.iter().all(|b| (b'0'..=b'7').contains(b)) {
+        return None;
+    }
+
+    let value = digits.iter().fold(0u32, |value, b| value * 8 + u32::from(b - b'0'));
+    if value < 0x20 || value == 0x7f {
+        char::from_u32(value)
+    } else {
+        None
+    }
+}
+
+fn backslash(i: &[u8]) -> Option<char> {
+    match i.first()? {
+        b'n' => Some('\n'),
+        b'r' => Some('\r'),
+        b't' => Some('\t'),
+        b'\\' => Some('\\'),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_rsyslog_escapes() {
+        let escapes = Escapes { rsyslog: true, backslash: false };
+
+        let cases = [
+            ("Request failed#012#011at Main.main(Main.java:7)", Some("Request failed\n\tat Main.main(Main.java:7)")),
+            ("line#015#012", Some("line\r\n")),
+            ("issue #101 and #01", None),
+            ("price #0a1 \\n", None),
+        ];
+
+        for (message, expected) in &cases {
+            assert_eq!(expected.map(str::to_owned), decode(message, escapes), "{}", message);
+        }
+    }
+
+    #[test]
+    fn decodes_backslash_escapes() {
+        let escapes = Escapes { rsyslog: false, backslash: true };
+
+        let cases = [
+            ("first\\nsecond\\tthird", Some("first\nsecond\tthird")),
+            ("C:\\\\new", Some("C:\\new")),
+            ("\\d+ #012", None),
+            ("trailing\\", None),
+        ];
+
+        for (message, expected) in &cases {
+            assert_eq!(expected.map(str::to_owned), decode(message, escapes), "{}", message);
+        }
+    }
+}
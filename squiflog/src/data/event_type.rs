@@ -0,0 +1,161 @@
+// Event types for messages that don't carry a template of their own.
+//
+// The shape of a message is its text with variable parts, like numbers, IP addresses,
+// hex values, and GUIDs masked out, so `connection from 192.0.2.1 port 22` and
+// `connection from 192.0.2.7 port 2222` both have the shape `connection from <ip> port <num>`.
+// The event type is a hash of the shape along with the app name that produced it.
+
+/**
+The shape of a message, with its variable parts masked out.
+*/
+pub(super) fn shape(message: &str) -> String {
+    let mut shape = String::with_capacity(message.len());
+
+    let mut rem = message;
+    while !rem.is_empty() {
+        let word_len = rem.find(|c: char| !is_word_char(c)).unwrap_or(rem.len());
+
+        // Separators, like the `:` before a port, aren't part of the word that follows them
+        if word_len == 0 || rem.starts_with(&['.', ':'][..]) {
+            let c = rem.chars().next().expect("missing char");
+            shape.push(c);
+            rem = &rem[c.len_utf8()..];
+            continue;
+        }
+
+        // Trailing punctuation, like the `.` at the end of a sentence, isn't part of the word
+        let word = rem[..word_len].trim_end_matches(&['.', ':', '-'][..]);
+        let word = if word.is_empty() { &rem[..word_len] } else { word };
+
+        shape.push_str(mask(word).unwrap_or(word));
+        rem = &rem[word.len()..];
+    }
+
+    shape
+}
+
+/**
+The message template for a shape.
+
+Braces in the shape are escaped so they aren't treated as holes.
+*/
+pub(super) fn template(shape: &str) -> String {
+    shape.replace('{', "{{").replace('}', "}}")
+}
+
+/**
+The event type for a shape produced by an app.
+
+Event types are the hex-encoded 32-bit FNV-1a hash of the app name and shape.
+*/
+pub(super) fn event_type(app_name: Option<&str>, shape: &str) -> String {
+    let mut hash: u32 = 0x811c_9dc5;
+    let bytes = app_name.unwrap_or("").bytes().chain(Some(0)).chain(shape.bytes());
+    for b in bytes {
+        hash ^= u32::from(b);
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+
+    format!("{:08x}", hash)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '.' || c == ':' || c == '-' || c == '_'
+}
+
+fn mask(word: &str) -> Option<&'static str> {
+    if is_guid(word) {
+        Some("<guid>")
+    } else if is_ipv4(word) || is_ipv6(word) {
+        Some("<ip>")
+    } else if is_number(word) {
+        Some("<num>")
+    } else if is_hex(word) {
+        Some("<hex>")
+    } else {
+        None
+    }
+}
+
+// `7d444840-9dc0-11d1-b245-5ffdce74fad2`
+fn is_guid(word: &str) -> bool {
+    let groups: Vec<&str> = word.split('-').collect();
+    groups.len() == 5
+        && groups.iter().zip(&[8, 4, 4, 4, 12]).all(|(group, len)| group.len() == *len && group.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+// `192.0.2.1`, optionally with a port, like `192.0.2.1:514`
+fn is_ipv4(word: &str) -> bool {
+    let addr = match word.rfind(':') {
+        Some(idx) if is_digits(&word[idx + 1..]) => &word[..idx],
+        _ => word,
+    };
+
+    let octets: Vec<&str> = addr.split('.').collect();
+    octets.len() == 4 && octets.iter().all(|octet| octet.len() <= 3 && is_digits(octet))
+}
+
+// `2001:db8::1`, where times like `22:14:15` are numbers instead
+fn is_ipv6(word: &str) -> bool {
+    word.matches(':').count() >= 2
+        && word.bytes().all(|b| b.is_ascii_hexdigit() || b == b':' || b == b'.')
+        && (word.contains("::") || word.bytes().any(|b| b.is_ascii_alphabetic()))
+}
+
+// `42`, `-1`, `3.14`, `1.2.3`, `22:14:15`, or `2023-10-11`
+fn is_number(word: &str) -> bool {
+    let digits = word.trim_start_matches('-');
+    digits.bytes().next().map(|b| b.is_ascii_digit()).unwrap_or(false)
+        && digits.bytes().all(|b| b.is_ascii_digit() || b == b'.' || b == b':' || b == b'-' || b == b',')
+}
+
+// `0x1f`, or a run of at least 8 hex digits with at least one digit, like `c000003e`
+fn is_hex(word: &str) -> bool {
+    if let Some(hex) = word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")) {
+        return !hex.is_empty() && hex.bytes().all(|b| b.is_ascii_hexdigit());
+    }
+
+    word.len() >= 8 && word.bytes().all(|b| b.is_ascii_hexdigit()) && word.bytes().any(|b| b.is_ascii_digit())
+}
+
+fn is_digits(i: &str) -> bool {
+    !i.is_empty() && i.bytes().all(|b| b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_variable_parts() {
+        let cases = [
+            ("Accepted publickey for root from 192.0.2.1 port 22 ssh2", "Accepted publickey for root from <ip> port <num> ssh2"),
+            ("connection to [2001:db8::1]:443 failed after 3.5s", "connection to [<ip>]:<num> failed after 3.5s"),
+            ("session 7d444840-9dc0-11d1-b245-5ffdce74fad2 expired.", "session <guid> expired."),
+            ("arch=c000003e syscall=59 pid=-1 addr=0x7ffe", "arch=<hex> syscall=<num> pid=<num> addr=<hex>"),
+            ("backup at 22:14:15 on 2023-10-11 from 10.0.0.1:514:", "backup at <num> on <num> from <ip>:"),
+            ("eth0 is up, deadbeef", "eth0 is up, deadbeef"),
+            ("", ""),
+        ];
+
+        for (message, expected) in &cases {
+            assert_eq!(*expected, shape(message), "{}", message);
+        }
+    }
+
+    #[test]
+    fn templates_escape_braces() {
+        assert_eq!("set {{ a = <num> }}", template(&shape("set { a = 1 }")));
+    }
+
+    #[test]
+    fn event_types_depend_on_app_name_and_shape() {
+        let sshd = event_type(Some("sshd"), &shape("Accepted publickey from 192.0.2.1"));
+
+        assert_eq!(8, sshd.len());
+        assert_eq!(sshd, event_type(Some("sshd"), &shape("Accepted publickey from 192.0.2.7")));
+        assert_ne!(sshd, event_type(Some("su"), &shape("Accepted publickey from 192.0.2.1")));
+        assert_ne!(sshd, event_type(Some("sshd"), &shape("Rejected publickey from 192.0.2.1")));
+        assert_ne!(event_type(Some("a"), "b"), event_type(Some("ab"), ""));
+    }
+}
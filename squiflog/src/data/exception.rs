@@ -0,0 +1,142 @@
+// Stack traces included in message text, like those logged by Java, .NET, and Python apps:
+//
+//   Request failed
+//   java.lang.IllegalStateException: connection closed
+//       at com.example.Client.send(Client.java:42)
+//       at com.example.Main.main(Main.java:7)
+//
+// The trace is split from the lines before it, which are kept as the message itself.
+
+/**
+A message split into its text and the stack trace that follows it.
+*/
+#[derive(Debug, PartialEq)]
+pub(super) struct Split<'a> {
+    pub message: &'a str,
+    pub exception: &'a str,
+}
+
+pub(super) fn split(message: &str) -> Option<Split<'_>> {
+    let message = message.trim_end();
+    let first_line_end = message.find('\n')?;
+    let first_line = message[..first_line_end].trim_end_matches('\r');
+
+    let start = trace_start(message)?;
+    if start == 0 {
+        // A Python trace doesn't have a useful first line, so its final exception line is used instead
+        let message_line = if is_python_traceback(first_line) {
+            message.lines().rev().map(str::trim).find(|line| !line.is_empty()).unwrap_or(first_line)
+        } else {
+            first_line
+        };
+
+        return Some(Split {
+            message: message_line,
+            exception: message,
+        });
+    }
+
+    Some(Split {
+        message: message[..start].trim(),
+        exception: &message[start..],
+    })
+}
+
+// The byte offset of the line that begins the trace, like `java.lang.Exception: message`
+fn trace_start(message: &str) -> Option<usize> {
+    let mut offset = 0;
+    let mut previous = None;
+
+    for line in message.split('\n') {
+        if is_python_traceback(line) {
+            return Some(offset);
+        }
+
+        // A trace begins with the exception line before its first frame
+        if is_frame(line) {
+            return previous;
+        }
+
+        previous = Some(offset);
+        offset += line.len() + 1;
+    }
+
+    None
+}
+
+// `at com.example.Main.main(Main.java:7)` or `   at Example.Program.Main(String[] args) in Program.cs:line 7`
+fn is_frame(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.len() < line.len() && trimmed.starts_with("at ") && trimmed.contains('(')
+}
+
+fn is_python_traceback(line: &str) -> bool {
+    line.trim() == "Traceback (most recent call last):"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_java_trace() {
+        let message = "Request failed\njava.lang.IllegalStateException: connection closed\n\tat com.example.Client.send(Client.java:42)\n\tat com.example.Main.main(Main.java:7)\nCaused by: java.io.IOException: reset\n\t... 2 more\n";
+
+        assert_eq!(Some(Split {
+            message: "Request failed",
+            exception: "java.lang.IllegalStateException: connection closed\n\tat com.example.Client.send(Client.java:42)\n\tat com.example.Main.main(Main.java:7)\nCaused by: java.io.IOException: reset\n\t... 2 more",
+        }), split(message));
+    }
+
+    #[test]
+    fn splits_trace_after_several_lines() {
+        let message = "Request failed\r\nretrying in 5s\r\njava.lang.IllegalStateException: connection closed\r\n\tat com.example.Client.send(Client.java:42)";
+
+        assert_eq!(Some(Split {
+            message: "Request failed\r\nretrying in 5s",
+            exception: "java.lang.IllegalStateException: connection closed\r\n\tat com.example.Client.send(Client.java:42)",
+        }), split(message));
+    }
+
+    #[test]
+    fn splits_trace_without_leading_message() {
+        let message = "System.InvalidOperationException: Sequence contains no elements\r\n   at System.Linq.Enumerable.First[TSource](IEnumerable`1 source)\r\n   at Example.Program.Main(String[] args) in Program.cs:line 7";
+
+        assert_eq!(Some(Split {
+            message: "System.InvalidOperationException: Sequence contains no elements",
+            exception: message,
+        }), split(message));
+    }
+
+    #[test]
+    fn splits_python_traceback() {
+        let message = "Traceback (most recent call last):\n  File \"app.py\", line 3, in <module>\n    main()\nValueError: invalid literal\n";
+
+        assert_eq!(Some(Split {
+            message: "ValueError: invalid literal",
+            exception: message.trim_end(),
+        }), split(message));
+
+        let message = "Unhandled error\nTraceback (most recent call last):\n  File \"app.py\", line 3, in <module>\nValueError: invalid literal";
+
+        assert_eq!(Some(Split {
+            message: "Unhandled error",
+            exception: "Traceback (most recent call last):\n  File \"app.py\", line 3, in <module>\nValueError: invalid literal",
+        }), split(message));
+    }
+
+    #[test]
+    fn ignores_messages_without_traces() {
+        let cases = [
+            "at 10:00 the job started",
+            "Job failed\nretrying at 10:00 (attempt 2)\n",
+            "first line\nsecond line",
+            "Job stopped\nat noon (UTC)",
+            "\tat com.example.Main.main(Main.java:7)",
+        ];
+
+        for case in &cases {
+            assert_eq!(None, split(case), "{}", case);
+        }
+    }
+}
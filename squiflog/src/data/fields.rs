@@ -0,0 +1,179 @@
+// Fields added, renamed, and dropped on every event, after it's been fully parsed.
+//
+// Fields are dropped first, then renamed, and then static properties are added, so
+// `SYSLOG_DROP_FIELDS` and `SYSLOG_RENAME_FIELDS` use the built-in names, like `hostname`.
+
+use std::{borrow::Cow, str::FromStr};
+
+use serde_json::Value;
+
+use crate::{
+    data::{clef, insert_property, Config},
+    error::{err_msg, Error},
+};
+
+/**
+Static properties to add to every event.
+
+Properties are a comma-separated list of `name=value` pairs, like `environment=production,site=syd`.
+*/
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Properties(Vec<(String, String)>);
+
+impl FromStr for Properties {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        pairs(s, "property").map(Properties)
+    }
+}
+
+/**
+Fields to rename on every event.
+
+Renames are a comma-separated list of `from=to` pairs, like `hostname=Host,app_name=Application`.
+*/
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Renames(Vec<(String, String)>);
+
+impl FromStr for Renames {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        pairs(s, "rename").map(Renames)
+    }
+}
+
+/**
+Fields to drop from every event.
+
+Drops are a comma-separated list of field names, like `facility,proc_id`. A field can be
+dropped only when it duplicates another field, like `message_id=app_name`.
+*/
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Drops(Vec<DropField>);
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct DropField {
+    field: String,
+    duplicate_of: Option<String>,
+}
+
+impl FromStr for Drops {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|drop| !drop.is_empty())
+            .map(|drop| {
+                let (field, duplicate_of) = match drop.find('=') {
+                    Some(eq) => (drop[..eq].trim(), Some(drop[eq + 1..].trim().to_owned())),
+                    None => (drop, None),
+                };
+
+                if field.is_empty() || duplicate_of.as_deref() == Some("") {
+                    return Err(err_msg(format!("invalid field to drop `{}`, expected `field` or `field=other_field`", drop)));
+                }
+
+                Ok(DropField {
+                    field: field.to_owned(),
+                    duplicate_of,
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Drops)
+    }
+}
+
+/**
+Drop, rename, and add fields on an event.
+
+Renamed and static properties that conflict with an existing property are added with a
+double-underscore prefix.
+*/
+pub(super) fn apply(clef: &mut clef::Message, config: &Config) {
+    for drop in &config.drop_fields.0 {
+        let is_dropped = match &drop.duplicate_of {
+            Some(other) => match clef.additional.get(drop.field.as_str()) {
+                Some(value) => clef.additional.get(other.as_str()) == Some(value),
+                None => false,
+            },
+            None => true,
+        };
+
+        if is_dropped {
+            clef.additional.remove(drop.field.as_str());
+        }
+    }
+
+    for (from, to) in &config.rename_fields.0 {
+        if let Some(value) = clef.additional.remove(from.as_str()) {
+            insert_property(clef, Cow::Owned(to.clone()), value);
+        }
+    }
+
+    for (name, value) in &config.static_properties.0 {
+        insert_property(clef, Cow::Owned(name.clone()), Value::String(value.clone()));
+    }
+}
+
+fn pairs(s: &str, kind: &str) -> Result<Vec<(String, String)>, Error> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let eq = pair.find('=').ok_or_else(|| err_msg(format!("invalid {} `{}`, expected `name=value`", kind, pair)))?;
+            let (name, value) = (pair[..eq].trim(), pair[eq + 1..].trim());
+
+            if name.is_empty() {
+                return Err(err_msg(format!("invalid {} `{}`, expected `name=value`", kind, pair)));
+            }
+
+            Ok((name.to_owned(), value.to_owned()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::Utc;
+    use serde_json::json;
+
+    use crate::data::Data;
+
+    #[test]
+    fn fields_are_dropped_renamed_and_added() {
+        let data = Data::new(Config {
+            static_properties: "environment=production, site=syd".parse().unwrap(),
+            rename_fields: "hostname=Host,proc_id=hostname".parse().unwrap(),
+            drop_fields: "facility,message_id=app_name".parse().unwrap(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let clef = data.to_clef(b"<34>1 2003-10-11T22:14:15.003Z host1 su 42 su - hello", &Utc::now());
+        let actual = serde_json::to_value(clef).unwrap();
+
+        assert_eq!(json!("host1"), actual["Host"]);
+        assert_eq!(json!("42"), actual["hostname"]);
+        assert_eq!(json!("production"), actual["environment"]);
+        assert_eq!(json!("syd"), actual["site"]);
+        assert!(actual.get("facility").is_none());
+        assert!(actual.get("message_id").is_none());
+
+        let clef = data.to_clef(b"<34>1 2003-10-11T22:14:15.003Z host1 su 42 ID47 - hello", &Utc::now());
+        let actual = serde_json::to_value(clef).unwrap();
+
+        assert_eq!(json!("ID47"), actual["message_id"]);
+    }
+
+    #[test]
+    fn invalid_fields_are_rejected() {
+        assert!("environment".parse::<Properties>().is_err());
+        assert!("=Host".parse::<Renames>().is_err());
+        assert!("message_id=".parse::<Drops>().is_err());
+    }
+}
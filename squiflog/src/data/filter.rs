@@ -0,0 +1,508 @@
+// Rules that drop noisy events before they're written, like debug messages, health checks,
+// and chatty cron jobs:
+//
+//   drop healthz message like '%/healthz%'
+//   keep cron-errors facility == 'cron' && severity <= 3
+//   drop cron facility == 'cron'
+//
+// Rules are evaluated in order against each event after it's been parsed and enriched, and
+// the first one whose expression matches decides whether the event is kept or dropped.
+// Events that don't match any rule are kept.
+//
+// Expressions compare fields with literals using `==`, `!=`, `<`, `<=`, `>`, `>=`, and `like`,
+// and combine them with `&&` (or `and`), `||` (or `or`), and `!` (or `not`). Fields are
+// `message`, `level`, `exception`, `event_type`, `facility` (the facility name), `severity`
+// (the numeric severity), and any other property by name.
+
+use std::{cmp::Ordering, collections::HashMap, str::FromStr, sync::Mutex};
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::{
+    data::{clef, syslog},
+    diagnostics::{Level, MIN_LEVEL},
+    error::{err_msg, Error},
+};
+
+lazy_static! {
+    static ref DROPPED: Mutex<HashMap<String, usize>> = Mutex::new(HashMap::new());
+}
+
+/**
+Rules for dropping or keeping events.
+
+Rules are written one per line as `drop` or `keep`, a name, and an expression, like
+`drop healthz message like '%/healthz%'`. Lines starting with `#` are ignored.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct FilterRules(Vec<FilterRule>);
+
+#[derive(Debug, Clone)]
+struct FilterRule {
+    name: String,
+    drop: bool,
+    expr: Expr,
+}
+
+impl FilterRules {
+    /**
+    Whether an event should be dropped.
+
+    The name of the rule that dropped the event is counted in diagnostics.
+    */
+    pub(super) fn is_dropped(&self, clef: &clef::Message, priority: &syslog::Priority) -> bool {
+        let event = Event { clef, priority };
+
+        match self.0.iter().find(|rule| rule.expr.eval(&event)) {
+            Some(rule) if rule.drop => {
+                record_drop(&rule.name);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for FilterRules {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let mut parts = line.splitn(3, char::is_whitespace);
+                let invalid = || err_msg(format!("invalid filter rule `{}`, expected `drop` or `keep`, a name, and an expression", line));
+
+                let drop = match parts.next() {
+                    Some("drop") => true,
+                    Some("keep") => false,
+                    _ => return Err(invalid()),
+                };
+                let name = parts.next().filter(|name| !name.is_empty()).ok_or_else(invalid)?;
+                let expr = parts.next().map(str::trim).filter(|expr| !expr.is_empty()).ok_or_else(invalid)?;
+
+                let expr = Parser::parse(expr).map_err(|err| err_msg(format!("invalid filter rule `{}`: {}", name, err)))?;
+
+                Ok(FilterRule {
+                    name: name.to_owned(),
+                    drop,
+                    expr,
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(FilterRules)
+    }
+}
+
+/**
+Take the number of events dropped by each rule since the last call.
+*/
+pub(crate) fn take() -> HashMap<String, usize> {
+    std::mem::take(&mut *DROPPED.lock().expect("filter lock poisoned"))
+}
+
+fn record_drop(name: &str) {
+    increment!(data.msg_dropped);
+
+    if MIN_LEVEL.includes(Level::Debug) {
+        let mut dropped = DROPPED.lock().expect("filter lock poisoned");
+
+        match dropped.get_mut(name) {
+            Some(count) => *count += 1,
+            None => {
+                dropped.insert(name.to_owned(), 1);
+            }
+        }
+    }
+}
+
+struct Event<'a, 'b> {
+    clef: &'a clef::Message<'b>,
+    priority: &'a syslog::Priority,
+}
+
+impl<'a, 'b> Event<'a, 'b> {
+    fn field(&self, name: &str) -> Value {
+        fn string(value: Option<&str>) -> Value {
+            value.map(|value| Value::String(value.to_owned())).unwrap_or(Value::Null)
+        }
+
+        match name {
+            "message" => string(self.clef.message.as_deref()),
+            "level" => string(self.clef.level.as_deref()),
+            "exception" => string(self.clef.exception.as_deref()),
+            "event_type" => string(self.clef.event_type.as_deref()),
+            "facility" => string(Some(self.priority.facility())),
+            "severity" => Value::from(self.priority.severity),
+            name => self.clef.additional.get(name).cloned().unwrap_or(Value::Null),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, CompareOp, Operand),
+    Like(Operand, Regex),
+    Truthy(Operand),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Literal(Value),
+    Field(String),
+}
+
+impl Expr {
+    fn eval(&self, event: &Event) -> bool {
+        match self {
+            Expr::Or(l, r) => l.eval(event) || r.eval(event),
+            Expr::And(l, r) => l.eval(event) && r.eval(event),
+            Expr::Not(expr) => !expr.eval(event),
+            Expr::Compare(l, op, r) => {
+                let ordering = compare(&l.eval(event), &r.eval(event));
+
+                match op {
+                    CompareOp::Eq => ordering == Some(Ordering::Equal),
+                    CompareOp::NotEq => ordering != Some(Ordering::Equal),
+                    CompareOp::Lt => ordering == Some(Ordering::Less),
+                    CompareOp::LtEq => matches!(ordering, Some(Ordering::Less) | Some(Ordering::Equal)),
+                    CompareOp::Gt => ordering == Some(Ordering::Greater),
+                    CompareOp::GtEq => matches!(ordering, Some(Ordering::Greater) | Some(Ordering::Equal)),
+                }
+            }
+            Expr::Like(operand, pattern) => match operand.eval(event) {
+                Value::String(value) => pattern.is_match(&value),
+                Value::Number(value) => pattern.is_match(&value.to_string()),
+                _ => false,
+            },
+            Expr::Truthy(operand) => match operand.eval(event) {
+                Value::Null | Value::Bool(false) => false,
+                Value::String(value) => !value.is_empty(),
+                _ => true,
+            },
+        }
+    }
+}
+
+impl Operand {
+    fn eval(&self, event: &Event) -> Value {
+        match self {
+            Operand::Literal(value) => value.clone(),
+            Operand::Field(name) => event.field(name),
+        }
+    }
+}
+
+// Numbers are compared with strings that look like numbers, so `status >= 500` matches a `status` of `"503"`
+fn compare(l: &Value, r: &Value) -> Option<Ordering> {
+    fn number(value: &Value) -> Option<f64> {
+        match value {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    match (l, r) {
+        (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+        (Value::Bool(l), Value::Bool(r)) => Some(l.cmp(r)),
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        (Value::Number(_), _) | (_, Value::Number(_)) => number(l)?.partial_cmp(&number(r)?),
+        _ => None,
+    }
+}
+
+// A `like` pattern, where `%` matches any sequence of characters and `_` matches any one
+// character, compared case-insensitively
+fn like_pattern(pattern: &str) -> Regex {
+    let mut regex = String::from("(?is)^");
+    for c in pattern.chars() {
+        match c {
+            '%' => regex.push_str(".*"),
+            '_' => regex.push('.'),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+
+    Regex::new(&regex).expect("invalid like pattern")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Str(String),
+    Num(f64),
+    Ident(String),
+    Op(&'static str),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Parser {
+    fn parse(expr: &str) -> Result<Expr, Error> {
+        let mut parser = Parser {
+            tokens: tokenize(expr)?,
+            next: 0,
+        };
+
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(err_msg(format!("unexpected {:?}", token))),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token
+    }
+
+    fn eat(&mut self, op: &str, keyword: Option<&str>) -> bool {
+        let is_match = match self.peek() {
+            Some(Token::Op(actual)) => *actual == op,
+            Some(Token::Ident(actual)) => Some(actual.as_str()) == keyword,
+            _ => false,
+        };
+
+        if is_match {
+            self.next += 1;
+        }
+        is_match
+    }
+
+    fn or(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.and()?;
+        while self.eat("||", Some("or")) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.not()?;
+        while self.eat("&&", Some("and")) {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, Error> {
+        if self.eat("!", Some("not")) {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+
+        if self.eat("(", None) {
+            let expr = self.or()?;
+            if !self.eat(")", None) {
+                return Err(err_msg("expected `)`"));
+            }
+
+            return Ok(expr);
+        }
+
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, Error> {
+        let l = self.operand()?;
+
+        let negated = matches!((self.peek(), self.tokens.get(self.next + 1)), (Some(Token::Ident(not)), Some(Token::Ident(like))) if not == "not" && like == "like");
+        if negated {
+            self.next += 1;
+        }
+
+        if self.eat("like", Some("like")) {
+            let like = match self.advance() {
+                Some(Token::Str(pattern)) => Expr::Like(l, like_pattern(&pattern)),
+                _ => return Err(err_msg("expected a string pattern after `like`")),
+            };
+
+            return Ok(if negated { Expr::Not(Box::new(like)) } else { like });
+        }
+
+        let op = match self.peek() {
+            Some(Token::Op("==")) => CompareOp::Eq,
+            Some(Token::Op("!=")) => CompareOp::NotEq,
+            Some(Token::Op("<")) => CompareOp::Lt,
+            Some(Token::Op("<=")) => CompareOp::LtEq,
+            Some(Token::Op(">")) => CompareOp::Gt,
+            Some(Token::Op(">=")) => CompareOp::GtEq,
+            _ => return Ok(Expr::Truthy(l)),
+        };
+        self.next += 1;
+
+        Ok(Expr::Compare(l, op, self.operand()?))
+    }
+
+    fn operand(&mut self) -> Result<Operand, Error> {
+        match self.advance() {
+            Some(Token::Str(value)) => Ok(Operand::Literal(Value::String(value))),
+            Some(Token::Num(value)) => Ok(Operand::Literal(Value::from(value))),
+            Some(Token::Ident(ident)) => Ok(match ident.as_str() {
+                "true" => Operand::Literal(Value::Bool(true)),
+                "false" => Operand::Literal(Value::Bool(false)),
+                "null" => Operand::Literal(Value::Null),
+                "and" | "or" | "not" | "like" => return Err(err_msg(format!("unexpected `{}`", ident))),
+                _ => Operand::Field(ident),
+            }),
+            Some(token) => Err(err_msg(format!("unexpected {:?}", token))),
+            None => Err(err_msg("unexpected end of expression")),
+        }
+    }
+}
+
+fn tokenize(expr: &str) -> Result<Vec<Token>, Error> {
+    const OPS: [&str; 12] = ["==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "(", ")", "="];
+
+    let mut tokens = vec![];
+    let mut rem = expr.trim_start();
+
+    while let Some(c) = rem.chars().next() {
+        if c == '\'' || c == '"' {
+            let mut value = String::new();
+            let mut chars = rem[1..].char_indices();
+            let end = loop {
+                match chars.next() {
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, escaped)) => value.push(escaped),
+                        None => return Err(err_msg("unterminated string")),
+                    },
+                    Some((i, actual)) if actual == c => break i,
+                    Some((_, actual)) => value.push(actual),
+                    None => return Err(err_msg("unterminated string")),
+                }
+            };
+
+            tokens.push(Token::Str(value));
+            rem = &rem[1 + end + 1..];
+        } else if c.is_ascii_digit() || (c == '-' && rem[1..].starts_with(|c: char| c.is_ascii_digit())) {
+            let len = 1 + rem[1..].find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rem.len() - 1);
+            let value = rem[..len].parse().map_err(|_| err_msg(format!("invalid number `{}`", &rem[..len])))?;
+
+            tokens.push(Token::Num(value));
+            rem = &rem[len..];
+        } else if c.is_alphabetic() || c == '_' || c == '@' {
+            let len = rem.find(|c: char| !c.is_alphanumeric() && c != '_' && c != '.' && c != '@').unwrap_or(rem.len());
+
+            tokens.push(Token::Ident(rem[..len].to_owned()));
+            rem = &rem[len..];
+        } else {
+            let op = OPS.iter().find(|op| rem.starts_with(**op)).ok_or_else(|| err_msg(format!("unexpected `{}`", c)))?;
+
+            // `=` is accepted as `==`
+            tokens.push(Token::Op(if *op == "=" { "==" } else { op }));
+            rem = &rem[op.len()..];
+        }
+
+        rem = rem.trim_start();
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{borrow::Cow, collections::HashMap};
+
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn is_match(expr: &str, clef: &clef::Message, priority: &syslog::Priority) -> bool {
+        Parser::parse(expr).unwrap().eval(&Event { clef, priority })
+    }
+
+    #[test]
+    fn expressions_match_events() {
+        let mut additional = HashMap::new();
+        additional.insert(Cow::Borrowed("app_name"), json!("CRON"));
+        additional.insert(Cow::Borrowed("status"), json!("503"));
+        additional.insert(Cow::Borrowed("bytes"), json!(2326));
+
+        let clef = clef::Message {
+            timestamp: Utc::now().into(),
+            level: Some(Cow::Borrowed("info")),
+            message: Some(Cow::Borrowed("GET /healthz HTTP/1.1")),
+            message_template: None,
+            exception: None,
+            event_type: None,
+            additional,
+        };
+        let priority = syslog::Priority { facility: 9, severity: 6 };
+
+        let cases = [
+            ("facility == 'cron' && severity >= 6", true),
+            ("facility == 'cron' and severity < 6", false),
+            ("message like '%healthz%'", true),
+            ("message like '%HEALTHZ%'", true),
+            ("message not like '%healthz%'", false),
+            ("message like 'GET _healthz%'", true),
+            ("message like 'GET _healthz'", false),
+            ("status >= 500 && bytes > 1000", true),
+            ("app_name == 'cron'", false),
+            ("app_name = \"CRON\"", true),
+            ("!(level == 'info') || missing", false),
+            ("missing == null && not exception", true),
+            ("severity != 6 or (bytes == 2326 and status != '200')", true),
+        ];
+
+        for (expr, expected) in &cases {
+            assert_eq!(*expected, is_match(expr, &clef, &priority), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn rules_parse() {
+        let rules: FilterRules = "# noise\ndrop healthz message like '%/healthz%'\n\nkeep cron-errors facility == 'cron' && severity <= 3\ndrop cron facility == 'cron'"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            vec![("healthz", true), ("cron-errors", false), ("cron", true)],
+            rules.0.iter().map(|rule| (rule.name.as_str(), rule.drop)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        let cases = [
+            "discard noise severity == 7",
+            "drop noise",
+            "drop noise severity ==",
+            "drop noise (severity == 7",
+            "drop noise message like 5",
+            "drop noise message == 'unterminated",
+            "drop noise severity == 7 7",
+        ];
+
+        for case in &cases {
+            assert!(case.parse::<FilterRules>().is_err(), "{}", case);
+        }
+    }
+}
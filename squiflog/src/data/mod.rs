@@ -1,57 +1,666 @@
 use std::{
+    borrow::Cow,
     collections::HashMap,
     io,
-    str,
-    io::Write
+    str::{
+        self,
+        FromStr,
+    },
+    io::Write,
+    sync::Arc,
+    thread,
+    time::Instant,
 };
 
 use serde_json::{
     self,
     json,
+    Value,
 };
 
-use crate::error::Error;
-use chrono::Utc;
+use crate::{
+    diagnostics::emit_err,
+    error::{
+        err_msg,
+        Error,
+    },
+};
+use chrono::{DateTime, Utc};
 
+pub mod charset;
 mod clef;
+mod escape;
+mod event_type;
+mod exception;
+pub mod fields;
+pub mod filter;
 mod parsers;
+mod profiles;
+pub mod reassembly;
+pub mod redact;
+pub mod rules;
+mod sd;
+pub mod selector;
+pub(crate) mod skew;
+mod split;
 pub mod syslog;
 
 metrics! {
-    msg
+    msg,
+    msg_rejected,
+    msg_reassembled,
+    msg_dropped,
+    msg_redacted
 }
 
 /**
 Configuration for CLEF formatting.
 */
-#[derive(Debug, Clone)]
-pub struct Config {}
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /**
+    How received messages are matched against the SYSLOG formats.
+    */
+    pub parse_mode: syslog::ParseMode,
+    /**
+    Whether RFC3164 messages take their app name and process id from their tag, like `app[42]:`.
+    */
+    pub parse_rfc3164_tags: bool,
+    /**
+    The charsets to decode message text that isn't valid UTF-8 with.
+    */
+    pub charsets: charset::Charsets,
+    /**
+    How SYSLOG structured data elements are represented as CLEF properties.
+    */
+    pub structured_data_format: StructuredDataFormat,
+    /**
+    Whether to split SD-IDs like `exampleSDID@32473` into a name and private enterprise number.
+    */
+    pub split_sd_enterprise_ids: bool,
+    /**
+    Whether to convert structured data param values that look like numbers or booleans.
+    */
+    pub infer_sd_param_types: bool,
+    /**
+    Whether to decode the SD-IDs registered by RFC5424 (`timeQuality`, `origin`, and `meta`) into typed properties.
+    */
+    pub decode_registered_sd_ids: bool,
+    /**
+    Whether to include the timestamp text and UTC offset a message was sent with.
+    */
+    pub original_timestamps: bool,
+    /**
+    Whether to include the time each message was received.
+    */
+    pub receive_time: bool,
+    /**
+    Which time to use as the timestamp of each event.
+    */
+    pub timestamp_policy: TimestampPolicy,
+    /**
+    The well-known vendor and application formats to recognize in SYSLOG messages.
+    */
+    pub profiles: Profiles,
+    /**
+    The sources to extract `key=value` pairs from the messages of.
+    */
+    pub key_value: selector::Selectors,
+    /**
+    User-defined rules for extracting properties from messages, tried before any profiles.
+    */
+    pub parse_rules: rules::ParseRules,
+    /**
+    The CLEF level to use for each SYSLOG severity.
+    */
+    pub level_mapping: LevelMapping,
+    /**
+    Whether to derive an event type from the shape of each message.
+    */
+    pub event_types: bool,
+    /**
+    Whether to include the shape of each message, with its variable parts masked, as its template.
+    */
+    pub masked_templates: bool,
+    /**
+    The escaped control characters to restore in message text.
+    */
+    pub escapes: Escapes,
+    /**
+    Whether to split stack traces in messages into the exception.
+    */
+    pub detect_exceptions: bool,
+    /**
+    Whether to split datagrams that contain several newline- or NUL-separated messages.
+    */
+    pub split_messages: bool,
+    /**
+    Static properties to add to every event.
+    */
+    pub static_properties: fields::Properties,
+    /**
+    Fields to rename on every event.
+    */
+    pub rename_fields: fields::Renames,
+    /**
+    Fields to drop from every event.
+    */
+    pub drop_fields: fields::Drops,
+    /**
+    Rules for dropping noisy events before they're written.
+    */
+    pub filter_rules: filter::FilterRules,
+    /**
+    Rules for redacting sensitive data from events before they're written.
+    */
+    pub redaction_rules: redact::RedactionRules,
+    /**
+    The key to hash values redacted by a `hash` rule with.
+    */
+    pub redaction_key: redact::Key,
+    /**
+    How events split across several messages are reassembled.
+    */
+    pub reassembly: reassembly::Config,
+}
+
+/**
+The set of well-known formats to recognize in SYSLOG messages.
+
+Each profile only applies to messages that match its format; others are
+left as they are.
+*/
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Profiles {
+    /**
+    CEE (lumberjack) JSON messages, like `@cee: {"msg": "connection accepted", "pri": 6}`.
+    */
+    pub cee: bool,
+    /**
+    Cisco IOS, ASA, and NX-OS messages, like `%LINK-3-UPDOWN: Interface ...`.
+    */
+    pub cisco: bool,
+    /**
+    ArcSight Common Event Format (CEF) messages, like `CEF:0|Vendor|Product|1.0|100|Name|5|src=10.0.0.1`.
+    */
+    pub cef: bool,
+    /**
+    IBM QRadar Log Event Extended Format (LEEF) messages, like `LEEF:2.0|Vendor|Product|1.0|41|^|src=192.0.2.0^sev=5`.
+    */
+    pub leef: bool,
+    /**
+    Linux audit records, like `type=SYSCALL msg=audit(1700000000.123:4567): arch=c000003e syscall=59`.
+    */
+    pub auditd: bool,
+    /**
+    Common and Combined Log Format access logs, like those from nginx and Apache.
+    */
+    pub clf: bool,
+    /**
+    HAProxy HTTP logs, like `192.0.2.1:33317 [06/Feb/2009:12:14:14.655] http-in static/srv1 10/0/30/69/109 200 2750 ...`.
+    */
+    pub haproxy: bool,
+}
+
+impl Profiles {
+    pub fn all() -> Self {
+        Profiles {
+            cee: true,
+            cisco: true,
+            cef: true,
+            leef: true,
+            auditd: true,
+            clf: true,
+            haproxy: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        Profiles {
+            cee: false,
+            cisco: false,
+            cef: false,
+            leef: false,
+            auditd: false,
+            clf: false,
+            haproxy: false,
+        }
+    }
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Profiles::none()
+    }
+}
+
+impl FromStr for Profiles {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "all" => return Ok(Profiles::all()),
+            "none" => return Ok(Profiles::none()),
+            _ => (),
+        }
+
+        let mut profiles = Profiles::none();
+        for profile in s.split(',') {
+            match profile.trim() {
+                "cee" => profiles.cee = true,
+                "cisco" => profiles.cisco = true,
+                "cef" => profiles.cef = true,
+                "leef" => profiles.leef = true,
+                "auditd" => profiles.auditd = true,
+                "clf" => profiles.clf = true,
+                "haproxy" => profiles.haproxy = true,
+                profile => return Err(err_msg(format!("unrecognized profile `{}`, expected `all`, `none`, or a list of `cee`, `cisco`, `cef`, `leef`, `auditd`, `clf`, or `haproxy`", profile))),
+            }
+        }
+
+        Ok(profiles)
+    }
+}
+
+/**
+Which time to use as the timestamp of each event.
+
+Policies are `message`, `received`, or `received-on-skew` with an optional maximum skew in
+seconds, like `received-on-skew=60`.
+*/
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TimestampPolicy {
+    /**
+    The time from the message, falling back to the receive time if it doesn't have one.
+    */
+    Message,
+    /**
+    The time the message was received.
+    */
+    Received,
+    /**
+    The time from the message, unless it's skewed from the receive time by more than the maximum.
+    */
+    ReceivedOnSkew {
+        max_skew_secs: u64,
+    },
+}
+
+impl Default for TimestampPolicy {
+    fn default() -> Self {
+        TimestampPolicy::Message
+    }
+}
+
+impl TimestampPolicy {
+    const DEFAULT_MAX_SKEW_SECS: u64 = 300;
+}
+
+impl FromStr for TimestampPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || err_msg("expected `message`, `received`, or `received-on-skew` with an optional maximum skew in seconds, like `received-on-skew=60`");
+
+        match s.trim() {
+            "message" => Ok(TimestampPolicy::Message),
+            "received" => Ok(TimestampPolicy::Received),
+            "received-on-skew" => Ok(TimestampPolicy::ReceivedOnSkew {
+                max_skew_secs: TimestampPolicy::DEFAULT_MAX_SKEW_SECS,
+            }),
+            policy => {
+                let max_skew_secs = policy.strip_prefix("received-on-skew=").ok_or_else(invalid)?;
+
+                Ok(TimestampPolicy::ReceivedOnSkew {
+                    max_skew_secs: max_skew_secs.trim().parse().map_err(|_| invalid())?,
+                })
+            }
+        }
+    }
+}
+
+/**
+The escaped control characters to restore in message text.
+
+Escapes are either `none`, or a comma-separated list of `rsyslog` and `backslash`.
+*/
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct Escapes {
+    /**
+    rsyslog's octal escapes, like `#011` for a tab and `#012` for a newline.
+    */
+    pub rsyslog: bool,
+    /**
+    Backslash escapes, like `\n`, `\r`, `\t`, and `\\`.
+    */
+    pub backslash: bool,
+}
+
+impl Escapes {
+    fn any(&self) -> bool {
+        self.rsyslog || self.backslash
+    }
+}
+
+impl FromStr for Escapes {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut escapes = Escapes::default();
+
+        if s.trim() == "none" {
+            return Ok(escapes);
+        }
+
+        for escape in s.split(',') {
+            match escape.trim() {
+                "rsyslog" => escapes.rsyslog = true,
+                "backslash" => escapes.backslash = true,
+                escape => return Err(err_msg(format!("unrecognized escape `{}`, expected `none`, or a list of `rsyslog` or `backslash`", escape))),
+            }
+        }
+
+        Ok(escapes)
+    }
+}
+
+/**
+The shape of structured data elements in CLEF.
+
+Given the element `[sdid1234 hello="world" ip="192.0.2.1" ip="192.0.2.129"]`:
+*/
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum StructuredDataFormat {
+    /**
+    A list of single-param objects, like `"sdid1234": [{ "hello": "world" }, { "ip": "192.0.2.1" }, { "ip": "192.0.2.129" }]`.
+    */
+    List,
+    /**
+    An object, collapsing repeated params into an array, like `"sdid1234": { "hello": "world", "ip": ["192.0.2.1", "192.0.2.129"] }`.
+    */
+    Object,
+    /**
+    A property per param, like `"sdid1234.hello": "world", "sdid1234.ip": ["192.0.2.1", "192.0.2.129"]`.
+    */
+    Flatten,
+}
+
+impl Default for StructuredDataFormat {
+    fn default() -> Self {
+        StructuredDataFormat::List
+    }
+}
+
+impl FromStr for StructuredDataFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "list" => Ok(StructuredDataFormat::List),
+            "object" => Ok(StructuredDataFormat::Object),
+            "flatten" => Ok(StructuredDataFormat::Flatten),
+            _ => Err(err_msg("expected `list`, `object`, or `flatten`")),
+        }
+    }
+}
+
+/**
+The CLEF level to use for each SYSLOG severity.
+
+Mappings are either the `syslog` severity names, the `seq` preset, or a comma-separated
+list of overrides for the severity names, like `emerg=Fatal,err=Error`.
+*/
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LevelMapping([Cow<'static, str>; 8]);
+
+impl LevelMapping {
+    /**
+    The SYSLOG severity names, like `emerg`, `err`, and `warning`.
+    */
+    pub fn syslog() -> Self {
+        let mut levels: [Cow<'static, str>; 8] = Default::default();
+        for (severity, level) in levels.iter_mut().enumerate() {
+            *level = Cow::Borrowed(syslog::Priority { facility: 0, severity: severity as u8 }.severity());
+        }
+
+        LevelMapping(levels)
+    }
+
+    /**
+    The levels understood natively by Seq.
+    */
+    pub fn seq() -> Self {
+        LevelMapping([
+            Cow::Borrowed("Fatal"),
+            Cow::Borrowed("Fatal"),
+            Cow::Borrowed("Fatal"),
+            Cow::Borrowed("Error"),
+            Cow::Borrowed("Warning"),
+            Cow::Borrowed("Information"),
+            Cow::Borrowed("Information"),
+            Cow::Borrowed("Debug"),
+        ])
+    }
+
+    /**
+    Map a SYSLOG severity name to its level.
 
-impl Default for Config {
+    Levels that aren't SYSLOG severity names are left as they are.
+    */
+    fn map<'a>(&self, level: Cow<'a, str>) -> Cow<'a, str> {
+        match severity_from_name(&level) {
+            Some(severity) => self.0[severity as usize].clone(),
+            None => level,
+        }
+    }
+}
+
+impl Default for LevelMapping {
     fn default() -> Self {
-        Config {}
+        LevelMapping::syslog()
+    }
+}
+
+impl FromStr for LevelMapping {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "syslog" => return Ok(LevelMapping::syslog()),
+            "seq" => return Ok(LevelMapping::seq()),
+            _ => (),
+        }
+
+        let mut mapping = LevelMapping::syslog();
+        for entry in s.split(',') {
+            let invalid = || err_msg(format!("invalid level mapping `{}`, expected `syslog`, `seq`, or a list of `severity=level`", entry.trim()));
+
+            let eq = entry.find('=').ok_or_else(invalid)?;
+            let (severity, level) = (entry[..eq].trim(), entry[eq + 1..].trim());
+
+            let severity = severity_from_name(severity)
+                .or_else(|| severity.parse().ok().filter(|severity| *severity <= 7))
+                .ok_or_else(invalid)?;
+            if level.is_empty() {
+                return Err(invalid());
+            }
+
+            mapping.0[severity as usize] = Cow::Owned(level.to_owned());
+        }
+
+        Ok(mapping)
+    }
+}
+
+// The number of digits in the fractional seconds of a timestamp, like `3` for `22:14:15.003Z`
+fn fraction_digits(timestamp: &str) -> usize {
+    match timestamp.find('.') {
+        Some(dot) => timestamp[dot + 1..].bytes().take_while(u8::is_ascii_digit).count(),
+        None => 0,
+    }
+}
+
+fn severity_from_name(name: &str) -> Option<u8> {
+    (0..8).find(|severity| syslog::Priority { facility: 0, severity: *severity }.severity() == name)
+}
+
+/**
+Add a property to an event without replacing an existing one.
+
+A conflicting property is added with a double-underscore prefix instead, like `__hostname`.
+*/
+pub(super) fn insert_property<'a>(clef: &mut clef::Message<'a>, k: Cow<'a, str>, v: Value) {
+    // CLEF reserves names beginning with `@`, and escapes others that do by doubling it
+    let k = if k.starts_with('@') { Cow::Owned(format!("@{}", k)) } else { k };
+
+    if clef.additional.contains_key(&k) {
+        clef.additional.insert(Cow::Owned(format!("__{}", k)), v);
+    } else {
+        clef.additional.insert(k, v);
     }
 }
 
 /**
 Build a CLEF processor to handle messages.
 */
-pub fn build(config: Config) -> Data {
-    Data::new(config)
+pub fn build(config: Config) -> Result<Data, Error> {
+    let data = Data::new(config)?;
+
+    // Events waiting for continuations are flushed in the background once they time out
+    if let Some(reassembler) = data.reassembler.clone() {
+        let data = data.clone();
+
+        thread::spawn(move || loop {
+            thread::sleep(reassembler.flush_interval());
+
+            for event in reassembler.flush_expired(Instant::now()) {
+                if let Err(err) = data.write_event(event) {
+                    emit_err(&err, "SYSLOG reassembled event could not be written");
+                }
+            }
+        });
+    }
+
+    Ok(data)
 }
 
 #[derive(Clone)]
-pub struct Data {}
+pub struct Data {
+    config: Config,
+    rules: rules::Rules,
+    reassembler: Option<Arc<reassembly::Reassembler<Parsed<'static>>>>,
+}
+
+/**
+A SYSLOG message converted into CLEF, before its continuations are joined and the rest
+of the processing is applied.
+*/
+#[derive(Debug)]
+struct Parsed<'a> {
+    clef: clef::Message<'a>,
+    priority: syslog::Priority,
+    has_message_time: bool,
+    rejection: Option<syslog::Rejection>,
+}
+
+impl<'a> Parsed<'a> {
+    fn into_owned(self) -> Parsed<'static> {
+        Parsed {
+            clef: self.clef.into_owned(),
+            priority: self.priority,
+            has_message_time: self.has_message_time,
+            rejection: self.rejection,
+        }
+    }
+}
 
 impl Data {
-    pub fn new(_: Config) -> Self {
-        Data {}
+    pub fn new(config: Config) -> Result<Self, Error> {
+        let rules = config.parse_rules.compile()?;
+        config.redaction_rules.check(&config.redaction_key)?;
+
+        let reassembler = if config.reassembly.is_enabled() {
+            Some(Arc::new(reassembly::Reassembler::new(&config.reassembly)?))
+        } else {
+            None
+        };
+
+        Ok(Data { config, rules, reassembler })
     }
 
     pub fn read_as_clef(&self, msg: &[u8]) -> Result<(), Error> {
+        if self.config.split_messages {
+            for msg in split::messages(msg) {
+                self.read_message(msg)?;
+            }
+
+            Ok(())
+        } else {
+            self.read_message(msg)
+        }
+    }
+
+    fn read_message(&self, msg: &[u8]) -> Result<(), Error> {
         increment!(data.msg);
-        let syslog = syslog::Message::from_bytes(msg);
-        let clef = syslog.into_clef();
+
+        match &self.reassembler {
+            Some(reassembler) => {
+                for event in self.reassemble(reassembler, msg, Instant::now()) {
+                    self.write_event(event)?;
+                }
+
+                Ok(())
+            }
+            None => self.write(self.to_clef_unless_dropped(msg, &Utc::now())),
+        }
+    }
+
+    fn reassemble(
+        &self,
+        reassembler: &reassembly::Reassembler<Parsed<'static>>,
+        msg: &[u8],
+        now: Instant,
+    ) -> Vec<reassembly::Event<Parsed<'static>>> {
+        let received = Utc::now();
+
+        // The indentation of a continuation is significant, so it's kept when the message is parsed
+        let (syslog, rejection) = syslog::Message::from_bytes_with_charsets(msg, self.config.parse_mode, &self.config.charsets, true, self.config.parse_rfc3164_tags, &received);
+
+        reassembler.push(syslog, received, now, |mut syslog| {
+            // The first message of an event is trimmed like any other
+            syslog.message = syslog.message.map(|message| match message {
+                Cow::Borrowed(message) => Cow::Borrowed(message.trim_start()),
+                Cow::Owned(message) => Cow::Owned(message.trim_start().to_owned()),
+            });
+
+            self.convert(syslog, rejection, &received).into_owned()
+        })
+    }
+
+    /**
+    Write any events still waiting for continuations.
+    */
+    pub fn flush(&self) -> Result<(), Error> {
+        if let Some(reassembler) = &self.reassembler {
+            for event in reassembler.flush_all() {
+                self.write_event(event)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_event(&self, event: reassembly::Event<Parsed<'static>>) -> Result<(), Error> {
+        self.write(self.complete(event.first, &event.continuations, &event.received))
+    }
+
+    fn write(&self, clef: Option<clef::Message>) -> Result<(), Error> {
+        // Events dropped by a filter rule aren't written
+        let clef = match clef {
+            Some(clef) => clef,
+            None => return Ok(()),
+        };
+
         let stdout = io::stdout();
         let mut stdout = stdout.lock();
 
@@ -60,6 +669,147 @@ impl Data {
 
         Ok(())
     }
+
+    #[cfg(test)]
+    fn to_clef<'a>(&self, msg: &'a [u8], received: &DateTime<Utc>) -> clef::Message<'a> {
+        self.to_clef_unless_dropped(msg, received).expect("event was dropped")
+    }
+
+    /**
+    Convert a message into CLEF.
+
+    If the event is dropped by a filter rule then `None` is returned.
+    */
+    fn to_clef_unless_dropped<'a>(&self, msg: &'a [u8], received: &DateTime<Utc>) -> Option<clef::Message<'a>> {
+        let (syslog, rejection) = syslog::Message::from_bytes_with_charsets(msg, self.config.parse_mode, &self.config.charsets, false, self.config.parse_rfc3164_tags, received);
+
+        self.complete(self.convert(syslog, rejection, received), &[], received)
+    }
+
+    fn convert<'a>(&self, syslog: syslog::Message<'a>, rejection: Option<syslog::Rejection>, received: &DateTime<Utc>) -> Parsed<'a> {
+        let has_message_time = syslog.timestamp_text.is_some();
+        let priority = syslog::Priority {
+            facility: syslog.priority.facility,
+            severity: syslog.priority.severity,
+        };
+
+        Parsed {
+            clef: syslog.into_clef(&self.config, received),
+            priority,
+            has_message_time,
+            rejection,
+        }
+    }
+
+    /**
+    Finish converting a message into CLEF, along with any continuations.
+
+    If the event is dropped by a filter rule then `None` is returned.
+    */
+    fn complete<'a>(&self, parsed: Parsed<'a>, continuations: &[String], received: &DateTime<Utc>) -> Option<clef::Message<'a>> {
+        let Parsed {
+            mut clef,
+            priority,
+            has_message_time,
+            rejection,
+        } = parsed;
+
+        if !continuations.is_empty() {
+            let mut message = clef.message.take().map(Cow::into_owned).unwrap_or_default();
+            for continuation in continuations {
+                message.push('\n');
+                message.push_str(continuation);
+            }
+
+            clef.message = Some(Cow::Owned(message));
+        }
+
+        if self.config.escapes.any() {
+            if let Some(message) = clef.message.as_deref().and_then(|message| escape::decode(message, self.config.escapes)) {
+                clef.message = Some(Cow::Owned(message));
+            }
+        }
+
+        profiles::apply(&mut clef, &self.config, &self.rules, received);
+
+        self.apply_receive_time(&mut clef, has_message_time, received);
+
+        if self.config.detect_exceptions && clef.exception.is_none() {
+            if let Some(split) = clef.message.as_deref().and_then(exception::split) {
+                let (message, exception) = (split.message.to_owned(), split.exception.to_owned());
+
+                clef.message = Some(Cow::Owned(message));
+                clef.exception = Some(Cow::Owned(exception));
+            }
+        }
+
+        clef.level = clef.level.map(|level| self.config.level_mapping.map(level));
+
+        if self.config.event_types || self.config.masked_templates {
+            if let Some(message) = &clef.message {
+                let shape = event_type::shape(message);
+
+                if self.config.event_types {
+                    let app_name = clef.additional.get("app_name").and_then(|app_name| app_name.as_str());
+                    clef.event_type = Some(event_type::event_type(app_name, &shape));
+                }
+                if self.config.masked_templates {
+                    clef.message_template = Some(Cow::Owned(event_type::template(&shape)));
+                }
+            }
+        }
+
+        if let Some(rejection) = rejection {
+            increment!(data.msg_rejected);
+            insert_property(&mut clef, Cow::Borrowed("rejection"), json!({
+                "format": rejection.format.name(),
+                "reason": rejection.reason,
+            }));
+        }
+
+        fields::apply(&mut clef, &self.config);
+
+        if self.config.filter_rules.is_dropped(&clef, &priority) {
+            return None;
+        }
+
+        // Redaction happens last so sensitive data added by any earlier step is caught
+        if redact::apply(&mut clef, &self.config) {
+            increment!(data.msg_redacted);
+        }
+
+        Some(clef)
+    }
+}
+
+impl Data {
+    fn apply_receive_time(&self, clef: &mut clef::Message, has_message_time: bool, received: &DateTime<Utc>) {
+        let skew = *received - clef.timestamp.value;
+        if has_message_time {
+            let hostname = clef.additional.get("hostname").and_then(|hostname| hostname.as_str());
+            skew::record(hostname, skew);
+        }
+
+        if self.config.receive_time {
+            insert_property(clef, Cow::Borrowed("received_at"), json!(received));
+        }
+
+        let use_receive_time = match self.config.timestamp_policy {
+            TimestampPolicy::Message => false,
+            TimestampPolicy::Received => true,
+            TimestampPolicy::ReceivedOnSkew { max_skew_secs } => has_message_time && skew.num_seconds().unsigned_abs() > max_skew_secs,
+        };
+
+        if use_receive_time {
+            // The message's own timestamp is kept when it's replaced, so the skew can still be seen
+            if has_message_time {
+                let message_timestamp = json!(clef.timestamp);
+                insert_property(clef, Cow::Borrowed("message_timestamp"), message_timestamp);
+            }
+
+            clef.timestamp = (*received).into();
+        }
+    }
 }
 
 impl<'a> syslog::Message<'a> {
@@ -79,13 +829,16 @@ impl<'a> syslog::Message<'a> {
 
     If fields conflict, then the lower-priority field is included with a
     double-underscore-prefixed name, e.g.: "__host".
+
+    Messages without a timestamp of their own are given the time they were received.
     */
-    pub fn into_clef(self) -> clef::Message<'a> {
+    pub fn into_clef(self, config: &Config, received: &DateTime<Utc>) -> clef::Message<'a> {
         #![deny(unused_variables)]
 
         let syslog::Message {
             priority,
             timestamp,
+            timestamp_text,
             hostname,
             app_name,
             proc_id,
@@ -96,44 +849,66 @@ impl<'a> syslog::Message<'a> {
 
         let mut additional = HashMap::new();
 
-        additional.insert("facility", json!(priority.facility()));
+        additional.insert(Cow::Borrowed("facility"), json!(priority.facility()));
+        additional.insert(Cow::Borrowed("facility_code"), json!(priority.facility));
+        additional.insert(Cow::Borrowed("severity_code"), json!(priority.severity));
         if let Some(hostname) = hostname {
-            additional.insert("hostname", json!(hostname));
+            additional.insert(Cow::Borrowed("hostname"), json!(hostname));
         }
         if let Some(app_name) = app_name {
-            additional.insert("app_name", json!(app_name));
+            additional.insert(Cow::Borrowed("app_name"), json!(app_name));
         }
         if let Some(proc_id) = proc_id {
-            additional.insert("proc_id", json!(proc_id));
+            additional.insert(Cow::Borrowed("proc_id"), json!(proc_id));
         }
         if let Some(message_id) = message_id {
-            additional.insert("message_id", json!(message_id));
+            additional.insert(Cow::Borrowed("message_id"), json!(message_id));
+        }
+        if let (true, Some(timestamp_text)) = (config.original_timestamps, timestamp_text) {
+            additional.insert(Cow::Borrowed("timestamp_text"), json!(timestamp_text));
+
+            if let Ok(timestamp) = DateTime::parse_from_rfc3339(timestamp_text) {
+                additional.insert(Cow::Borrowed("timestamp_offset"), json!(timestamp.offset().to_string()));
+            }
         }
 
         if let Some(sd) = structured_data {
+            let mut origin_ip = None;
+
             for element in sd {
-                let mut params = vec![];
-                for (k, v) in element.params {
-                    let mut map = HashMap::new();
-                    map.insert(k, v);
-                    params.push(map);
+                if config.decode_registered_sd_ids && hostname.is_none() && origin_ip.is_none() {
+                    origin_ip = sd::origin_ip(&element);
                 }
-                additional.insert(element.id, json!(params));
+
+                sd::into_clef(element, config, &mut additional);
+            }
+
+            // Fall back to the originator's IP address to identify the host
+            if let Some(origin_ip) = origin_ip {
+                additional.insert(Cow::Borrowed("hostname"), json!(origin_ip));
             }
         }
 
         clef::Message {
-            timestamp: timestamp.unwrap_or_else(|| Utc::now()),
-            level: Some(priority.severity()),
+            timestamp: match timestamp {
+                Some(timestamp) => clef::Timestamp {
+                    value: timestamp,
+                    fraction_digits: timestamp_text.map(fraction_digits),
+                },
+                None => (*received).into(),
+            },
+            level: Some(Cow::Borrowed(priority.severity())),
             message,
             message_template: None,
             exception: None,
+            event_type: None,
             additional,
         }
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::vec_init_then_push)]
 mod test {
     use super::*;
     use serde_json::{
@@ -150,6 +925,8 @@ mod test {
             "@m": "hello world",
             "@t": "2020-02-13T00:51:39.527825Z",
             "facility": "daemon",
+            "facility_code": 3,
+            "severity_code": 6,
             "hostname": "docker-desktop",
             "app_name": "8b1089798cf8",
             "proc_id": "1481",
@@ -164,6 +941,7 @@ mod test {
                 severity: 6,
             },
             timestamp: to_timestamp("2020-02-13T00:51:39.527825Z"),
+            timestamp_text: Some("2020-02-13T00:51:39.527825Z"),
             hostname: Some("docker-desktop"),
             app_name: Some("8b1089798cf8"),
             proc_id: Some("1481"),
@@ -172,12 +950,30 @@ mod test {
             message: Some(Borrowed(message)),
         };
 
-        let clef = syslog.into_clef();
+        let clef = syslog.into_clef(&Config::default(), &Utc::now());
         let actual = serde_json::to_value(clef).unwrap();
 
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn rejected_syslog_to_clef() {
+        let data = Data::new(Config {
+            parse_mode: syslog::ParseMode::Strict5424,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let clef = data.to_clef(b"<34>Oct 11 22:14:15 mymachine su: 'su root' failed", &Utc::now());
+        let actual = serde_json::to_value(clef).unwrap();
+
+        assert_eq!(json!({
+            "format": "RFC5424",
+            "reason": "invalid message, version not 1",
+        }), actual["rejection"]);
+        assert_eq!(json!("<34>Oct 11 22:14:15 mymachine su: 'su root' failed"), actual["@m"]);
+    }
+
     #[test]
     fn syslog_to_clef_with_structured_data() {
         let expected = json!({
@@ -185,6 +981,8 @@ mod test {
             "@m": "hello world",
             "@t": "2020-02-13T00:51:39.527825Z",
             "facility": "daemon",
+            "facility_code": 3,
+            "severity_code": 6,
             "hostname": "docker-desktop",
             "app_name": "8b1089798cf8",
             "proc_id": "1481",
@@ -204,6 +1002,7 @@ mod test {
                 severity: 6,
             },
             timestamp: to_timestamp("2020-02-13T00:51:39.527825Z"),
+            timestamp_text: Some("2020-02-13T00:51:39.527825Z"),
             hostname: Some("docker-desktop"),
             app_name: Some("8b1089798cf8"),
             proc_id: Some("1481"),
@@ -215,7 +1014,7 @@ mod test {
             message: Some(Borrowed(message)),
         };
 
-        let clef = syslog.into_clef();
+        let clef = syslog.into_clef(&Config::default(), &Utc::now());
         let actual = serde_json::to_value(clef).unwrap();
 
         assert_eq!(expected, actual);
@@ -228,6 +1027,8 @@ mod test {
             "@m": "hello world",
             "@t": "2020-02-13T00:51:39.527825Z",
             "facility": "daemon",
+            "facility_code": 3,
+            "severity_code": 6,
             "hostname": "docker-desktop",
             "app_name": "8b1089798cf8",
             "proc_id": "1481",
@@ -247,6 +1048,7 @@ mod test {
                 severity: 6,
             },
             timestamp: to_timestamp("2020-02-13T00:51:39.527825Z"),
+            timestamp_text: Some("2020-02-13T00:51:39.527825Z"),
             hostname: Some("docker-desktop"),
             app_name: Some("8b1089798cf8"),
             proc_id: Some("1481"),
@@ -258,9 +1060,352 @@ mod test {
             message: Some(Borrowed(message)),
         };
 
-        let clef = syslog.into_clef();
+        let clef = syslog.into_clef(&Config::default(), &Utc::now());
         let actual = serde_json::to_value(clef).unwrap();
 
         assert_eq!(expected, actual);
     }
+
+    fn syslog_with_repeated_structured_data() -> syslog::Message<'static> {
+        let sd_params = vec![
+            ("hello", "world".to_owned()),
+            ("ip", "192.0.2.1".to_owned()),
+            ("ip", "192.0.2.129".to_owned()),
+        ];
+
+        syslog::Message {
+            priority: syslog::Priority {
+                facility: 3,
+                severity: 6,
+            },
+            timestamp: to_timestamp("2020-02-13T00:51:39.527825Z"),
+            timestamp_text: Some("2020-02-13T00:51:39.527825Z"),
+            hostname: Some("docker-desktop"),
+            app_name: None,
+            proc_id: None,
+            message_id: None,
+            structured_data: Some(vec![syslog::StructuredDataElement {
+                id: "sdid1234",
+                params: sd_params,
+            }]),
+            message: Some(Borrowed("hello world")),
+        }
+    }
+
+    #[test]
+    fn syslog_to_clef_with_structured_data_as_object() {
+        let expected = json!({
+            "@l": "info",
+            "@m": "hello world",
+            "@t": "2020-02-13T00:51:39.527825Z",
+            "facility": "daemon",
+            "facility_code": 3,
+            "severity_code": 6,
+            "hostname": "docker-desktop",
+            "sdid1234": { "hello": "world", "ip": ["192.0.2.1", "192.0.2.129"] }
+        });
+
+        let config = Config {
+            structured_data_format: StructuredDataFormat::Object,
+            ..Default::default()
+        };
+
+        let clef = syslog_with_repeated_structured_data().into_clef(&config, &Utc::now());
+        let actual = serde_json::to_value(clef).unwrap();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn syslog_to_clef_with_structured_data_flattened() {
+        let expected = json!({
+            "@l": "info",
+            "@m": "hello world",
+            "@t": "2020-02-13T00:51:39.527825Z",
+            "facility": "daemon",
+            "facility_code": 3,
+            "severity_code": 6,
+            "hostname": "docker-desktop",
+            "sdid1234.hello": "world",
+            "sdid1234.ip": ["192.0.2.1", "192.0.2.129"]
+        });
+
+        let config = Config {
+            structured_data_format: StructuredDataFormat::Flatten,
+            ..Default::default()
+        };
+
+        let clef = syslog_with_repeated_structured_data().into_clef(&config, &Utc::now());
+        let actual = serde_json::to_value(clef).unwrap();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn syslog_to_clef_falls_back_to_origin_ip_for_hostname() {
+        let syslog = syslog::Message::from_bytes(b"<165>1 2003-10-11T22:14:15.003Z - evntslog - ID47 [origin ip=\"192.0.2.1\" software=\"test\"] An event");
+
+        let config = Config {
+            decode_registered_sd_ids: true,
+            ..Default::default()
+        };
+
+        let clef = syslog.into_clef(&config, &Utc::now());
+        let actual = serde_json::to_value(clef).unwrap();
+
+        assert_eq!(json!("192.0.2.1"), actual["hostname"]);
+        assert_eq!(json!({ "ip": "192.0.2.1", "software": "test" }), actual["origin"]);
+    }
+
+    #[test]
+    fn syslog_to_clef_keeps_registered_structured_data_as_is_by_default() {
+        let syslog = syslog::Message::from_bytes(b"<165>1 2003-10-11T22:14:15.003Z - evntslog - ID47 [origin ip=\"192.0.2.1\"][meta sequenceId=\"29\"] An event");
+
+        let clef = syslog.into_clef(&Config::default(), &Utc::now());
+        let actual = serde_json::to_value(clef).unwrap();
+
+        assert_eq!(Value::Null, actual["hostname"]);
+        assert_eq!(json!([{ "ip": "192.0.2.1" }]), actual["origin"]);
+        assert_eq!(json!([{ "sequenceId": "29" }]), actual["meta"]);
+    }
+
+    #[test]
+    fn syslog_to_clef_with_seq_levels() {
+        let data = Data::new(Config {
+            level_mapping: LevelMapping::seq(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let clef = data.to_clef(b"<34>Oct 11 22:14:15 mymachine su: 'su root' failed", &Utc::now());
+        let actual = serde_json::to_value(clef).unwrap();
+
+        assert_eq!(json!("Fatal"), actual["@l"]);
+        assert_eq!(json!(2), actual["severity_code"]);
+        assert_eq!(json!(4), actual["facility_code"]);
+        assert_eq!(json!("auth"), actual["facility"]);
+    }
+
+    #[test]
+    fn level_mapping_from_str() {
+        assert_eq!(LevelMapping::syslog(), "syslog".parse().unwrap());
+        assert_eq!(LevelMapping::seq(), " seq ".parse().unwrap());
+
+        let mapping: LevelMapping = "emerg=Fatal, 3=Error".parse().unwrap();
+        assert_eq!("Fatal", mapping.map(Cow::Borrowed("emerg")));
+        assert_eq!("Error", mapping.map(Cow::Borrowed("err")));
+        assert_eq!("warning", mapping.map(Cow::Borrowed("warning")));
+        assert_eq!("Verbose", mapping.map(Cow::Borrowed("Verbose")));
+
+        for mapping in &["unknown=Fatal", "8=Fatal", "emerg", "emerg="] {
+            assert!(mapping.parse::<LevelMapping>().is_err(), "{}", mapping);
+        }
+    }
+
+    #[test]
+    fn syslog_to_clef_with_event_types() {
+        let data = Data::new(Config {
+            event_types: true,
+            masked_templates: true,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let first = serde_json::to_value(data.to_clef(b"<34>1 2003-10-11T22:14:15.003Z host1 sshd - - - Accepted publickey from 192.0.2.1 port 22", &Utc::now())).unwrap();
+        let second = serde_json::to_value(data.to_clef(b"<34>1 2003-10-11T22:14:16.003Z host2 sshd - - - Accepted publickey from 192.0.2.7 port 2222", &Utc::now())).unwrap();
+
+        assert_eq!(json!("Accepted publickey from 192.0.2.1 port 22"), first["@m"]);
+        assert_eq!(json!("Accepted publickey from <ip> port <num>"), first["@mt"]);
+        assert!(first["@i"].is_string());
+        assert_eq!(first["@i"], second["@i"]);
+    }
+
+    #[test]
+    fn syslog_to_clef_with_exception() {
+        let data = Data::new(Config {
+            detect_exceptions: true,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let clef = data.to_clef(b"<27>1 2003-10-11T22:14:15.003Z host1 app 42 - - Request failed\njava.lang.IllegalStateException: closed\n\tat com.example.Client.send(Client.java:42)", &Utc::now());
+        let actual = serde_json::to_value(clef).unwrap();
+
+        assert_eq!(json!("Request failed"), actual["@m"]);
+        assert_eq!(json!("java.lang.IllegalStateException: closed\n\tat com.example.Client.send(Client.java:42)"), actual["@x"]);
+    }
+
+    #[test]
+    fn syslog_to_clef_with_reassembled_continuations() {
+        let data = Data::new(Config {
+            detect_exceptions: true,
+            reassembly: reassembly::Config {
+                start_patterns: vec![r"^\S".to_owned()],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+
+        let reassembler = data.reassembler.as_ref().unwrap();
+        let now = Instant::now();
+
+        data.reassemble(reassembler, b"<27>1 2003-10-11T22:14:15.003Z host1 app 42 - - Request failed", now);
+        data.reassemble(reassembler, b"<27>1 2003-10-11T22:14:15.003Z host1 app 42 - - java.lang.IllegalStateException: closed", now);
+        data.reassemble(reassembler, b"<27>1 2003-10-11T22:14:15.004Z host1 app 42 - - \tat com.example.Client.send(Client.java:42)", now);
+
+        let mut complete = reassembler.flush_all();
+        assert_eq!(1, complete.len());
+
+        let event = complete.remove(0);
+        let clef = data.complete(event.first, &event.continuations, &event.received).unwrap();
+        let actual = serde_json::to_value(clef).unwrap();
+
+        assert_eq!(json!("java.lang.IllegalStateException: closed"), actual["@m"]);
+        assert_eq!(json!("java.lang.IllegalStateException: closed\n\tat com.example.Client.send(Client.java:42)"), actual["@x"]);
+    }
+
+    #[test]
+    fn syslog_to_clef_with_rsyslog_escapes() {
+        let data = Data::new(Config {
+            escapes: Escapes { rsyslog: true, backslash: false },
+            detect_exceptions: true,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let clef = data.to_clef(b"<27>Oct 11 22:14:15 host1 app[42]: Request failed#012java.lang.IllegalStateException: closed#012#011at com.example.Client.send(Client.java:42)", &Utc::now());
+        let actual = serde_json::to_value(clef).unwrap();
+
+        assert_eq!(json!("app[42]: Request failed"), actual["@m"]);
+        assert_eq!(json!("java.lang.IllegalStateException: closed\n\tat com.example.Client.send(Client.java:42)"), actual["@x"]);
+    }
+
+    #[test]
+    fn escapes_from_str() {
+        assert_eq!(Escapes::default(), "none".parse::<Escapes>().unwrap());
+        assert_eq!(Escapes { rsyslog: true, backslash: true }, "rsyslog, backslash".parse::<Escapes>().unwrap());
+        assert!("octal".parse::<Escapes>().is_err());
+    }
+
+    #[test]
+    fn syslog_to_clef_with_original_timestamp() {
+        let data = Data::new(Config {
+            original_timestamps: true,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let clef = data.to_clef(b"<165>1 2003-08-24T05:14:15.000300-07:00 192.0.2.1 myproc 8710 - - hello", &Utc::now());
+        let actual = serde_json::to_value(clef).unwrap();
+
+        assert_eq!(json!("2003-08-24T12:14:15.000300Z"), actual["@t"]);
+        assert_eq!(json!("2003-08-24T05:14:15.000300-07:00"), actual["timestamp_text"]);
+        assert_eq!(json!("-07:00"), actual["timestamp_offset"]);
+
+        let clef = data.to_clef(b"<34>Oct 11 22:14:15 mymachine su: hello", &Utc::now());
+        let actual = serde_json::to_value(clef).unwrap();
+
+        assert_eq!(json!("Oct 11 22:14:15"), actual["timestamp_text"]);
+        assert!(actual.get("timestamp_offset").is_none());
+        assert!(!actual["@t"].as_str().unwrap().contains('.'));
+    }
+
+    #[test]
+    fn syslog_to_clef_with_receive_time() {
+        let received = to_timestamp("2003-10-11T22:24:15.003Z").unwrap();
+        let msg = b"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 - hello";
+
+        let data = Data::new(Config {
+            receive_time: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let actual = serde_json::to_value(data.to_clef(msg, &received)).unwrap();
+
+        assert_eq!(json!("2003-10-11T22:14:15.003Z"), actual["@t"]);
+        assert_eq!(json!("2003-10-11T22:24:15.003Z"), actual["received_at"]);
+
+        let cases = [
+            (TimestampPolicy::Received, "2003-10-11T22:24:15.003Z"),
+            (TimestampPolicy::ReceivedOnSkew { max_skew_secs: 300 }, "2003-10-11T22:24:15.003Z"),
+            (TimestampPolicy::ReceivedOnSkew { max_skew_secs: 900 }, "2003-10-11T22:14:15.003Z"),
+        ];
+
+        for (timestamp_policy, expected) in &cases {
+            let data = Data::new(Config {
+                timestamp_policy: *timestamp_policy,
+                ..Default::default()
+            })
+            .unwrap();
+            let actual = serde_json::to_value(data.to_clef(msg, &received)).unwrap();
+
+            assert_eq!(json!(expected), actual["@t"], "{:?}", timestamp_policy);
+        }
+    }
+
+    #[test]
+    fn syslog_to_clef_without_timestamp_uses_receive_time() {
+        let received = to_timestamp("2003-10-11T22:24:15.003Z").unwrap();
+
+        let data = Data::new(Config {
+            receive_time: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let actual = serde_json::to_value(data.to_clef(b"<165>1 - mymachine.example.com evntslog - ID47 - hello", &received)).unwrap();
+
+        assert_eq!(json!("2003-10-11T22:24:15.003Z"), actual["@t"]);
+        assert_eq!(actual["@t"], actual["received_at"]);
+    }
+
+    #[test]
+    fn syslog_to_clef_receive_time_does_not_replace_properties() {
+        let received = to_timestamp("2003-10-11T22:24:15.003Z").unwrap();
+        let msg = b"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [received_at by=\"relay\"][message_timestamp at=\"relay\"] hello";
+
+        let data = Data::new(Config {
+            receive_time: true,
+            timestamp_policy: TimestampPolicy::Received,
+            ..Default::default()
+        })
+        .unwrap();
+        let actual = serde_json::to_value(data.to_clef(msg, &received)).unwrap();
+
+        assert_eq!(json!([{ "by": "relay" }]), actual["received_at"]);
+        assert_eq!(json!("2003-10-11T22:24:15.003Z"), actual["__received_at"]);
+        assert_eq!(json!([{ "at": "relay" }]), actual["message_timestamp"]);
+        assert_eq!(json!("2003-10-11T22:14:15.003Z"), actual["__message_timestamp"]);
+    }
+
+    #[test]
+    fn timestamp_policy_from_str() {
+        assert_eq!(TimestampPolicy::Received, "received".parse::<TimestampPolicy>().unwrap());
+        assert_eq!(TimestampPolicy::ReceivedOnSkew { max_skew_secs: 300 }, "received-on-skew".parse::<TimestampPolicy>().unwrap());
+        assert_eq!(TimestampPolicy::ReceivedOnSkew { max_skew_secs: 60 }, "received-on-skew=60".parse::<TimestampPolicy>().unwrap());
+        assert!("received-on-skew=soon".parse::<TimestampPolicy>().is_err());
+    }
+
+    #[test]
+    fn syslog_to_clef_with_filter_rules() {
+        let data = Data::new(Config {
+            static_properties: "environment=production".parse().unwrap(),
+            filter_rules: "keep cron-errors facility == 'cron' && severity <= 3\ndrop cron facility == 'cron'\ndrop production-debug environment == 'production' && level == 'debug'".parse().unwrap(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let cases: [(&[u8], bool); 4] = [
+            (b"<78>Oct 11 22:14:15 host1 CRON[42]: (root) CMD (run-parts /etc/cron.hourly)", true),
+            (b"<75>Oct 11 22:14:15 host1 CRON[42]: (root) MAIL (mailed 1 byte of output)", false),
+            (b"<15>Oct 11 22:14:15 host1 app[42]: cache miss", true),
+            (b"<14>Oct 11 22:14:15 host1 app[42]: request complete", false),
+        ];
+
+        for (msg, is_dropped) in &cases {
+            let clef = data.to_clef_unless_dropped(msg, &Utc::now());
+
+            assert_eq!(*is_dropped, clef.is_none(), "{}", String::from_utf8_lossy(msg));
+        }
+    }
 }
@@ -0,0 +1,392 @@
+// Web server and proxy access logs, in the Common or Combined Log Format used by nginx and Apache:
+//
+//   192.0.2.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326 "http://example.com/" "Mozilla/5.0"
+//
+// and the default HTTP log format of HAProxy:
+//
+//   192.0.2.1:33317 [06/Feb/2009:12:14:14.655] http-in static/srv1 10/0/30/69/109 200 2750 - - ---- 1/1/1/1/0 0/0 "GET /index.html HTTP/1.1"
+//
+// Either may follow a leading tag, like `nginx: ` or `haproxy[42]: `.
+
+use std::net::IpAddr;
+
+#[derive(Debug, PartialEq)]
+pub struct Message<'a> {
+    pub client_ip: &'a str,
+    pub client_port: Option<u16>,
+    pub user: Option<&'a str>,
+    pub request: Request<'a>,
+    pub status: u16,
+    pub bytes: Option<u64>,
+    pub referer: Option<&'a str>,
+    pub user_agent: Option<&'a str>,
+    pub proxy: Option<Proxy<'a>>,
+}
+
+impl<'a> Message<'a> {
+    /**
+    The equivalent SYSLOG severity for the class of the response status.
+    */
+    pub fn syslog_severity(&self) -> u8 {
+        match self.status {
+            500..=599 => 3,
+            400..=499 => 4,
+            _ => 6,
+        }
+    }
+}
+
+/**
+The HTTP request line, like `GET /index.html HTTP/1.1`.
+
+Malformed requests, like `-` or `<BADREQ>`, only have the raw line.
+*/
+#[derive(Debug, PartialEq)]
+pub struct Request<'a> {
+    pub line: &'a str,
+    pub method: Option<&'a str>,
+    pub path: Option<&'a str>,
+    pub protocol: Option<&'a str>,
+}
+
+/**
+The routing and timing details of a request passed through HAProxy.
+*/
+#[derive(Debug, PartialEq)]
+pub struct Proxy<'a> {
+    pub frontend: &'a str,
+    pub backend: &'a str,
+    pub server: &'a str,
+    pub timers: Timers,
+    pub termination_state: &'a str,
+}
+
+/**
+The HAProxy timers, in milliseconds.
+
+Timers that never started, logged as `-1`, are empty.
+*/
+#[derive(Debug, Default, PartialEq)]
+pub struct Timers {
+    pub request: Option<u64>,
+    pub queue: Option<u64>,
+    pub connect: Option<u64>,
+    pub response: Option<u64>,
+    pub total: Option<u64>,
+}
+
+pub fn clf(i: &str) -> Option<Message<'_>> {
+    clf_after_tag(i).or_else(|| clf_after_tag(skip_tag(i)?))
+}
+
+pub fn haproxy(i: &str) -> Option<Message<'_>> {
+    haproxy_after_tag(i).or_else(|| haproxy_after_tag(skip_tag(i)?))
+}
+
+fn clf_after_tag(i: &str) -> Option<Message<'_>> {
+    let (client_ip, rem) = token(i)?;
+    let (_ident, rem) = token(rem)?;
+    let (user, rem) = token(rem)?;
+    let (_time, rem) = bracketed(rem)?;
+    let (request, rem) = quoted(rem)?;
+    let (status, rem) = token(rem)?;
+    let (bytes, rem) = token(rem)?;
+
+    // The referer and user agent are only present in the Combined Log Format
+    let (referer, user_agent) = match quoted(rem) {
+        Some((referer, rem)) => (Some(referer), quoted(rem).map(|(user_agent, _)| user_agent)),
+        None if rem.trim().is_empty() => (None, None),
+        None => return None,
+    };
+
+    if !is_ip(client_ip) {
+        return None;
+    }
+
+    Some(Message {
+        client_ip,
+        client_port: None,
+        user: not_empty(user),
+        request: request_line(request),
+        status: status_code(status)?,
+        bytes: byte_count(bytes)?,
+        referer: referer.and_then(not_empty),
+        user_agent: user_agent.and_then(not_empty),
+        proxy: None,
+    })
+}
+
+fn haproxy_after_tag(i: &str) -> Option<Message<'_>> {
+    let (client, rem) = token(i)?;
+    let (_accept_date, rem) = bracketed(rem)?;
+    let (frontend, rem) = token(rem)?;
+    let (backend_server, rem) = token(rem)?;
+    let (timers, rem) = token(rem)?;
+    let (status, rem) = token(rem)?;
+    let (bytes, rem) = token(rem)?;
+    let (_request_cookie, rem) = token(rem)?;
+    let (_response_cookie, rem) = token(rem)?;
+    let (termination_state, rem) = token(rem)?;
+    let (_connections, rem) = token(rem)?;
+    let (_queues, rem) = token(rem)?;
+
+    // Captured headers, like `{example.com}`, come before the request
+    let mut rem = rem.trim_start();
+    while rem.starts_with('{') {
+        rem = &rem[rem.find('}')? + 1..];
+        rem = rem.trim_start();
+    }
+    let (request, _) = quoted(rem)?;
+
+    let port_idx = client.rfind(':')?;
+    let (client_ip, client_port) = (&client[..port_idx], &client[port_idx + 1..]);
+    let client_ip = client_ip.trim_start_matches('[').trim_end_matches(']');
+
+    let server_idx = backend_server.find('/')?;
+
+    if !is_ip(client_ip) {
+        return None;
+    }
+
+    Some(Message {
+        client_ip,
+        client_port: Some(client_port.parse().ok()?),
+        user: None,
+        request: request_line(request),
+        status: status_code(status)?,
+        bytes: byte_count(bytes.trim_start_matches('+'))?,
+        referer: None,
+        user_agent: None,
+        proxy: Some(Proxy {
+            frontend,
+            backend: &backend_server[..server_idx],
+            server: &backend_server[server_idx + 1..],
+            timers: haproxy_timers(timers)?,
+            termination_state,
+        }),
+    })
+}
+
+// `TR/Tw/Tc/Tr/Ta`, where each timer may be prefixed by `+` or be `-1`
+fn haproxy_timers(i: &str) -> Option<Timers> {
+    let mut timers = i.split('/').map(|timer| match timer.trim_start_matches('+').parse::<i64>() {
+        Ok(timer) if timer >= 0 => Some(Some(timer as u64)),
+        Ok(_) => Some(None),
+        Err(_) => None,
+    });
+
+    let mut next = || timers.next().flatten();
+    let parsed = Timers {
+        request: next()?,
+        queue: next()?,
+        connect: next()?,
+        response: next()?,
+        total: next()?,
+    };
+
+    if timers.next().is_some() {
+        return None;
+    }
+
+    Some(parsed)
+}
+
+fn request_line(line: &str) -> Request<'_> {
+    let mut parts = line.split(' ');
+    let (method, path, protocol) = (parts.next(), parts.next(), parts.next());
+
+    let is_method = |method: &str| !method.is_empty() && method.bytes().all(|b| b.is_ascii_uppercase());
+    match (method, path) {
+        (Some(method), Some(path)) if is_method(method) && !path.is_empty() && parts.next().is_none() => Request {
+            line,
+            method: Some(method),
+            path: Some(path),
+            protocol,
+        },
+        _ => Request {
+            line,
+            method: None,
+            path: None,
+            protocol: None,
+        },
+    }
+}
+
+fn status_code(i: &str) -> Option<u16> {
+    match i.parse() {
+        Ok(status) if (100..=599).contains(&status) => Some(status),
+        _ => None,
+    }
+}
+
+// Responses without a body are logged with a byte count of `-`
+fn byte_count(i: &str) -> Option<Option<u64>> {
+    if i == "-" {
+        return Some(None);
+    }
+
+    i.parse().ok().map(Some)
+}
+
+fn not_empty(i: &str) -> Option<&str> {
+    if i.is_empty() || i == "-" {
+        None
+    } else {
+        Some(i)
+    }
+}
+
+fn is_ip(i: &str) -> bool {
+    i.parse::<IpAddr>().is_ok()
+}
+
+// Skip a leading tag, like `nginx: ` or `haproxy[42]: `
+fn skip_tag(i: &str) -> Option<&str> {
+    let (tag, rem) = token(i)?;
+    if tag.len() > 1 && tag.ends_with(':') {
+        Some(rem)
+    } else {
+        None
+    }
+}
+
+fn token(i: &str) -> Option<(&str, &str)> {
+    let i = i.trim_start_matches(' ');
+    let end = i.find(' ').unwrap_or(i.len());
+    if end == 0 {
+        return None;
+    }
+
+    Some((&i[..end], &i[end..]))
+}
+
+// `[10/Oct/2000:13:55:36 -0700]`
+fn bracketed(i: &str) -> Option<(&str, &str)> {
+    let i = i.trim_start_matches(' ').strip_prefix('[')?;
+    let end = i.find(']')?;
+
+    Some((&i[..end], &i[end + 1..]))
+}
+
+// `"GET / HTTP/1.1"`, where quotes in the value may be escaped as `\"`
+fn quoted(i: &str) -> Option<(&str, &str)> {
+    let i = i.trim_start_matches(' ').strip_prefix('"')?;
+
+    let mut escaped = false;
+    for (idx, b) in i.bytes().enumerate() {
+        match b {
+            _ if escaped => escaped = false,
+            b'\\' => escaped = true,
+            b'"' => return Some((&i[..idx], &i[idx + 1..])),
+            _ => (),
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_combined_log_format() {
+        let msg = clf(r#"nginx: 192.0.2.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif?a=1 HTTP/1.0" 200 2326 "http://example.com/start.html" "Mozilla/4.08 [en] (Win98; I ;Nav)""#)
+            .expect("failed to parse message");
+
+        assert_eq!(Message {
+            client_ip: "192.0.2.1",
+            client_port: None,
+            user: Some("frank"),
+            request: Request {
+                line: "GET /apache_pb.gif?a=1 HTTP/1.0",
+                method: Some("GET"),
+                path: Some("/apache_pb.gif?a=1"),
+                protocol: Some("HTTP/1.0"),
+            },
+            status: 200,
+            bytes: Some(2326),
+            referer: Some("http://example.com/start.html"),
+            user_agent: Some("Mozilla/4.08 [en] (Win98; I ;Nav)"),
+            proxy: None,
+        }, msg);
+        assert_eq!(6, msg.syslog_severity());
+    }
+
+    #[test]
+    fn parses_common_log_format() {
+        let msg = clf(r#"2001:db8::1 - - [10/Oct/2000:13:55:36 -0700] "-" 400 -"#).expect("failed to parse message");
+
+        assert_eq!("2001:db8::1", msg.client_ip);
+        assert_eq!(None, msg.user);
+        assert_eq!(None, msg.request.method);
+        assert_eq!(400, msg.status);
+        assert_eq!(None, msg.bytes);
+        assert_eq!(None, msg.referer);
+        assert_eq!(4, msg.syslog_severity());
+    }
+
+    #[test]
+    fn parses_haproxy_http_log_format() {
+        let msg = haproxy(r#"haproxy[14389]: 192.0.2.1:33317 [06/Feb/2009:12:14:14.655] http-in static/srv1 10/0/30/69/109 503 +2750 - - SC-- 1/1/1/1/0 0/0 {1wt.eu} {} "GET /index.html HTTP/1.1""#)
+            .expect("failed to parse message");
+
+        assert_eq!(Message {
+            client_ip: "192.0.2.1",
+            client_port: Some(33317),
+            user: None,
+            request: Request {
+                line: "GET /index.html HTTP/1.1",
+                method: Some("GET"),
+                path: Some("/index.html"),
+                protocol: Some("HTTP/1.1"),
+            },
+            status: 503,
+            bytes: Some(2750),
+            referer: None,
+            user_agent: None,
+            proxy: Some(Proxy {
+                frontend: "http-in",
+                backend: "static",
+                server: "srv1",
+                timers: Timers {
+                    request: Some(10),
+                    queue: Some(0),
+                    connect: Some(30),
+                    response: Some(69),
+                    total: Some(109),
+                },
+                termination_state: "SC--",
+            }),
+        }, msg);
+        assert_eq!(3, msg.syslog_severity());
+    }
+
+    #[test]
+    fn haproxy_timers_may_not_have_started() {
+        let msg = haproxy(r#"[2001:db8::1]:80 [06/Feb/2009:12:14:14.655] http-in static/<NOSRV> 0/-1/-1/-1/0 400 187 - - PR-- 1/1/0/0/0 0/0 "<BADREQ>""#)
+            .expect("failed to parse message");
+
+        assert_eq!("2001:db8::1", msg.client_ip);
+        assert_eq!(Timers { request: Some(0), total: Some(0), ..Default::default() }, msg.proxy.expect("missing proxy").timers);
+        assert_eq!(None, msg.request.method);
+    }
+
+    #[test]
+    fn rejects_other_messages() {
+        let cases = [
+            "su: 'su root' failed for lonvick on /dev/pts/8",
+            r#"192.0.2.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 999 0"#,
+            r#"192.0.2.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 0 extra"#,
+            r#"not-an-ip - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 0"#,
+            r#"cafe - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 0"#,
+            r#"192.0.2 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 0"#,
+            r#"192.0.2.1 - - 10/Oct/2000:13:55:36 "GET / HTTP/1.0" 200 0"#,
+        ];
+
+        for case in &cases {
+            assert_eq!(None, clf(case), "{}", case);
+            assert_eq!(None, haproxy(case), "{}", case);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+// Linux audit records, as forwarded through syslog by auditd or audisp, like:
+//
+//   type=SYSCALL msg=audit(1700000000.123:4567): arch=c000003e syscall=59 success=yes comm="ls" exe="/usr/bin/ls"
+//   node=host1 type=USER_LOGIN msg=audit(1700000000.123:4568): pid=42 msg='op=login acct="root" res=success'
+//
+// The `audit(<seconds>.<millis>:<serial>)` stamp identifies the event a record belongs to,
+// so all records with the same serial can be correlated. Untrusted strings are either
+// double-quoted or hex-encoded, and user-space records nest their own fields in a single-quoted `msg`.
+
+use chrono::{DateTime, TimeZone, Utc};
+
+// Fields that auditd hex-encodes when they contain spaces or control characters
+const ENCODED_FIELDS: &[&str] = &["proctitle", "cmd", "comm", "exe", "cwd", "name", "path", "acct", "key"];
+
+// Separates the raw fields from those interpreted by the `ENRICHED` log format
+const ENRICHED_SEPARATOR: char = '\u{1d}';
+
+#[derive(Debug, PartialEq)]
+pub struct Message<'a> {
+    pub node: Option<&'a str>,
+    pub record_type: &'a str,
+    pub timestamp: DateTime<Utc>,
+    pub serial: u64,
+    pub fields: Vec<(&'a str, String)>,
+}
+
+pub fn message(i: &str) -> Option<Message<'_>> {
+    let stamp_start = i.find("msg=audit(")?;
+
+    // The record type, and optionally the node, come right before the stamp
+    let mut header = i[..stamp_start].split_whitespace().rev();
+    let record_type = header.next()?.strip_prefix("type=").filter(|t| is_key(t))?;
+    let node = header.next().and_then(|node| node.strip_prefix("node="));
+
+    let rem = &i[stamp_start + "msg=audit(".len()..];
+    let stamp_end = rem.find("):")?;
+    let (timestamp, serial) = stamp(&rem[..stamp_end])?;
+
+    Some(Message {
+        node,
+        record_type,
+        timestamp,
+        serial,
+        fields: fields(&rem[stamp_end + "):".len()..]),
+    })
+}
+
+// `1700000000.123:4567`
+fn stamp(i: &str) -> Option<(DateTime<Utc>, u64)> {
+    let (time, serial) = split_once(i, ':')?;
+    let (secs, millis) = split_once(time, '.')?;
+
+    if millis.len() != 3 || !millis.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let timestamp = Utc.timestamp_opt(secs.parse().ok()?, millis.parse::<u32>().ok()? * 1_000_000).single()?;
+    Some((timestamp, serial.parse().ok()?))
+}
+
+fn fields(i: &str) -> Vec<(&str, String)> {
+    let mut fields = vec![];
+    let mut rem = i;
+
+    loop {
+        rem = rem.trim_start_matches(|c: char| c.is_whitespace() || c == ENRICHED_SEPARATOR);
+        if rem.is_empty() {
+            break;
+        }
+
+        let key_len = rem.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-')).unwrap_or(rem.len());
+        if key_len == 0 || !rem[key_len..].starts_with('=') {
+            // Skip over any text that isn't a field, like the `{ read }` of an AVC record
+            let end = rem.find(|c: char| c.is_whitespace() || c == ENRICHED_SEPARATOR).unwrap_or(rem.len());
+            rem = &rem[end..];
+            continue;
+        }
+
+        let key = &rem[..key_len];
+        let value = &rem[key_len + 1..];
+
+        if let Some(quoted) = value.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            fields.push((key, quoted[..end].to_owned()));
+            rem = quoted.get(end + 1..).unwrap_or("");
+        } else if let Some(nested) = value.strip_prefix('\'') {
+            // User-space records nest their fields in a single-quoted value
+            let end = nested.find('\'').unwrap_or(nested.len());
+            fields.extend(self::fields(&nested[..end]));
+            rem = nested.get(end + 1..).unwrap_or("");
+        } else {
+            let end = value.find(|c: char| c.is_whitespace() || c == ENRICHED_SEPARATOR).unwrap_or(value.len());
+            let raw = &value[..end];
+
+            // Unquoted values of string fields are always hex-encoded
+            let decoded = if ENCODED_FIELDS.contains(&key) { decode_hex(raw) } else { None };
+
+            fields.push((key, decoded.unwrap_or_else(|| raw.to_owned())));
+            rem = &value[end..];
+        }
+    }
+
+    fields
+}
+
+// Arguments in hex-encoded fields like `proctitle` are separated by nul bytes
+fn decode_hex(i: &str) -> Option<String> {
+    if i.is_empty() || i.len() % 2 != 0 || !i.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let bytes = (0..i.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(&i[idx..idx + 2], 16))
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+
+    let decoded = String::from_utf8_lossy(&bytes);
+    Some(decoded.trim_end_matches('\0').replace('\0', " "))
+}
+
+fn is_key(i: &str) -> bool {
+    !i.is_empty() && i.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
+
+fn split_once(i: &str, delim: char) -> Option<(&str, &str)> {
+    let idx = i.find(delim)?;
+    Some((&i[..idx], &i[idx + delim.len_utf8()..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(fields: &[(&'static str, &str)]) -> Vec<(&'static str, String)> {
+        fields.iter().map(|(k, v)| (*k, v.to_string())).collect()
+    }
+
+    #[test]
+    fn parses_syscall_record() {
+        let msg = message(r#"type=SYSCALL msg=audit(1700000000.123:4567): arch=c000003e syscall=59 success=yes comm="ls" exe="/usr/bin/ls" key=(null)"#)
+            .expect("failed to parse message");
+
+        assert_eq!(Message {
+            node: None,
+            record_type: "SYSCALL",
+            timestamp: Utc.timestamp(1_700_000_000, 123_000_000),
+            serial: 4567,
+            fields: fields(&[
+                ("arch", "c000003e"),
+                ("syscall", "59"),
+                ("success", "yes"),
+                ("comm", "ls"),
+                ("exe", "/usr/bin/ls"),
+                ("key", "(null)"),
+            ]),
+        }, msg);
+    }
+
+    #[test]
+    fn decodes_hex_encoded_fields() {
+        let msg = message("audispd: node=host1 type=PROCTITLE msg=audit(1700000000.123:4567): proctitle=6C73002D6C61002F746D70 a0=2f").expect("failed to parse message");
+
+        assert_eq!(Some("host1"), msg.node);
+        assert_eq!("PROCTITLE", msg.record_type);
+        assert_eq!(fields(&[("proctitle", "ls -la /tmp"), ("a0", "2f")]), msg.fields);
+    }
+
+    #[test]
+    fn flattens_nested_user_fields() {
+        let msg = message(r#"type=USER_LOGIN msg=audit(1700000000.123:4568): pid=42 uid=0 msg='op=login acct="root" exe="/usr/sbin/sshd" res=success'"#)
+            .expect("failed to parse message");
+
+        assert_eq!(fields(&[
+            ("pid", "42"),
+            ("uid", "0"),
+            ("op", "login"),
+            ("acct", "root"),
+            ("exe", "/usr/sbin/sshd"),
+            ("res", "success"),
+        ]), msg.fields);
+    }
+
+    #[test]
+    fn parses_enriched_fields() {
+        let msg = message("type=SYSCALL msg=audit(1700000000.123:4567): arch=c000003e syscall=59\u{1d}ARCH=x86_64 SYSCALL=execve").expect("failed to parse message");
+
+        assert_eq!(fields(&[
+            ("arch", "c000003e"),
+            ("syscall", "59"),
+            ("ARCH", "x86_64"),
+            ("SYSCALL", "execve"),
+        ]), msg.fields);
+    }
+
+    #[test]
+    fn skips_text_between_fields() {
+        let msg = message(r#"type=AVC msg=audit(1700000000.123:4569): avc:  denied  { read } for  pid=42 comm="cat""#).expect("failed to parse message");
+
+        assert_eq!(fields(&[("pid", "42"), ("comm", "cat")]), msg.fields);
+    }
+
+    #[test]
+    fn rejects_other_messages() {
+        let cases = [
+            "su: 'su root' failed for lonvick on /dev/pts/8",
+            "type=SYSCALL arch=c000003e",
+            "msg=audit(1700000000.123:4567): missing type",
+            "type=SYSCALL msg=audit(1700000000:4567): arch=c000003e",
+            "type=SYSCALL msg=audit(1700000000.123): arch=c000003e",
+        ];
+
+        for case in &cases {
+            assert_eq!(None, message(case), "{}", case);
+        }
+    }
+}
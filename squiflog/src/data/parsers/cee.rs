@@ -0,0 +1,142 @@
+// CEE (lumberjack) message bodies, as produced by rsyslog's mmjsonparse convention, like:
+//
+//   @cee: {"msg": "connection accepted", "pri": 6, "time": "2023-10-11T22:14:15.003Z", "src": "10.0.0.1"}
+//
+// The `@cee:` cookie is followed by a JSON object. The `msg`, `pri` or `severity`,
+// and `time` fields have CLEF equivalents, everything else is an ordinary property.
+
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+
+use super::find_prefix;
+
+const COOKIE: &str = "@cee:";
+
+#[derive(Debug, PartialEq)]
+pub struct Message {
+    pub msg: Option<String>,
+    pub severity: Option<u8>,
+    pub time: Option<DateTime<Utc>>,
+    pub fields: Map<String, Value>,
+}
+
+pub fn message(i: &str) -> Option<Message> {
+    let start = find_prefix(i, COOKIE)?;
+    let mut fields: Map<String, Value> = serde_json::from_str(i[start + COOKIE.len()..].trim()).ok()?;
+
+    let msg = take_if(&mut fields, "msg", |v| v.as_str().map(str::to_owned));
+    let severity = take_if(&mut fields, "severity", severity).or_else(|| take_if(&mut fields, "pri", pri));
+    let time = take_if(&mut fields, "time", |v| {
+        DateTime::parse_from_rfc3339(v.as_str()?).ok().map(|ts| ts.with_timezone(&Utc))
+    });
+
+    Some(Message {
+        msg,
+        severity,
+        time,
+        fields,
+    })
+}
+
+// Fields that can't be converted are left as ordinary properties
+fn take_if<T>(fields: &mut Map<String, Value>, k: &str, convert: impl FnOnce(&Value) -> Option<T>) -> Option<T> {
+    let converted = convert(fields.get(k)?)?;
+    fields.remove(k);
+    Some(converted)
+}
+
+// A severity, either as a number from `0` to `7` or its name, like `err`
+fn severity(v: &Value) -> Option<u8> {
+    if let Some(severity) = number(v) {
+        return if severity <= 7 { Some(severity as u8) } else { None };
+    }
+
+    match &*v.as_str()?.trim().to_ascii_lowercase() {
+        "emerg" | "panic" => Some(0),
+        "alert" => Some(1),
+        "crit" | "critical" => Some(2),
+        "err" | "error" => Some(3),
+        "warning" | "warn" => Some(4),
+        "notice" => Some(5),
+        "info" => Some(6),
+        "debug" => Some(7),
+        _ => None,
+    }
+}
+
+// A SYSLOG priority, either as a number combining the facility and severity, or a severity name
+fn pri(v: &Value) -> Option<u8> {
+    match number(v) {
+        Some(pri) if pri <= 191 => Some((pri % 8) as u8),
+        Some(_) => None,
+        None => severity(v),
+    }
+}
+
+fn number(v: &Value) -> Option<u64> {
+    match v {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn parses_cee_message() {
+        let msg = message(r#"@cee: {"msg": "connection accepted", "pri": 30, "time": "2023-10-11T22:14:15.003+02:00", "src": "10.0.0.1"}"#)
+            .expect("failed to parse message");
+
+        assert_eq!(Some("connection accepted".to_owned()), msg.msg);
+        assert_eq!(Some(6), msg.severity);
+        assert_eq!(Some(Utc.ymd(2023, 10, 11).and_hms_milli(20, 14, 15, 3)), msg.time);
+        assert_eq!(json!({ "src": "10.0.0.1" }), Value::Object(msg.fields));
+    }
+
+    #[test]
+    fn parses_cee_message_after_tag() {
+        let msg = message(r#"sshd[123]: @cee:{"msg":"accepted","severity":"warn"}"#).expect("failed to parse message");
+
+        assert_eq!(Some("accepted".to_owned()), msg.msg);
+        assert_eq!(Some(4), msg.severity);
+        assert!(msg.fields.is_empty());
+    }
+
+    #[test]
+    fn severity_takes_precedence_over_pri() {
+        let msg = message(r#"@cee: {"severity": 3, "pri": "info"}"#).expect("failed to parse message");
+
+        assert_eq!(Some(3), msg.severity);
+        assert_eq!(json!({ "pri": "info" }), Value::Object(msg.fields));
+    }
+
+    #[test]
+    fn unrecognized_fields_are_kept() {
+        let msg = message(r#"@cee: {"msg": 42, "severity": 12, "time": "yesterday"}"#).expect("failed to parse message");
+
+        assert_eq!(None, msg.msg);
+        assert_eq!(None, msg.severity);
+        assert_eq!(None, msg.time);
+        assert_eq!(json!({ "msg": 42, "severity": 12, "time": "yesterday" }), Value::Object(msg.fields));
+    }
+
+    #[test]
+    fn rejects_other_messages() {
+        let cases = [
+            r#"{"msg": "plain json"}"#,
+            r#"@cee: not json"#,
+            r#"@cee: ["not", "an", "object"]"#,
+            r#"user@cee: {"msg": "not a cookie"}"#,
+            r#"app: forwarded @cee: {"msg": "not at the start"}"#,
+        ];
+
+        for case in &cases {
+            assert_eq!(None, message(case), "{}", case);
+        }
+    }
+}
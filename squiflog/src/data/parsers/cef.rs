@@ -0,0 +1,263 @@
+// ArcSight Common Event Format (CEF) message bodies, like:
+//
+//   CEF:0|Security|threatmanager|1.0|100|worm successfully stopped|10|src=10.0.0.1 dst=2.1.2.2 spt=1232
+//
+// The header fields are separated by `|`, and the extension is a list of `key=value`
+// pairs separated by spaces, where values may contain unescaped spaces themselves.
+
+use super::find_format_prefix;
+
+#[derive(Debug, PartialEq)]
+pub struct Message {
+    pub version: String,
+    pub device_vendor: String,
+    pub device_product: String,
+    pub device_version: String,
+    pub signature_id: String,
+    pub name: String,
+    pub severity: String,
+    pub extension: Vec<(String, String)>,
+}
+
+impl Message {
+    /**
+    The equivalent SYSLOG severity for the CEF severity.
+
+    CEF severities are either a number from `0` to `10`, or one of `Low`, `Medium`,
+    `High`, or `Very-High`.
+    */
+    pub fn syslog_severity(&self) -> Option<u8> {
+        let severity = self.severity.trim();
+
+        let normalized = match severity.parse::<u8>() {
+            Ok(0..=3) => "low",
+            Ok(4..=6) => "medium",
+            Ok(7..=8) => "high",
+            Ok(9..=10) => "very-high",
+            Ok(_) => return None,
+            Err(_) => severity,
+        };
+
+        match &*normalized.to_ascii_lowercase() {
+            "low" => Some(6),
+            "medium" => Some(4),
+            "high" => Some(3),
+            "very-high" => Some(2),
+            _ => None,
+        }
+    }
+}
+
+pub fn message(i: &str) -> Option<Message> {
+    let start = find_format_prefix(i, "CEF:")?;
+
+    let mut fields = Vec::with_capacity(7);
+    let mut rem = &i[start + "CEF:".len()..];
+    while fields.len() < 7 {
+        let end = find_unescaped(rem, b'|')?;
+        fields.push(unescape(&rem[..end]));
+        rem = &rem[end + 1..];
+    }
+
+    let mut fields = fields.into_iter();
+    let mut next = || fields.next().expect("missing CEF header field");
+
+    Some(Message {
+        version: next(),
+        device_vendor: next(),
+        device_product: next(),
+        device_version: next(),
+        signature_id: next(),
+        name: next(),
+        severity: next(),
+        extension: extension(rem),
+    })
+}
+
+/**
+Parse the `key=value` pairs of a CEF extension.
+
+Where an extension contains custom fields along with their labels, like `cs1=Block cs1Label=Action`,
+the label is used as the key in place of the custom field name.
+*/
+pub fn extension(i: &str) -> Vec<(String, String)> {
+    let keys = find_keys(i);
+
+    let mut pairs = Vec::with_capacity(keys.len());
+    for (idx, &(key_start, key_end)) in keys.iter().enumerate() {
+        let value_end = keys.get(idx + 1).map(|&(next, _)| next).unwrap_or_else(|| i.len());
+        let value = i[key_end + 1..value_end].trim_end_matches(' ');
+
+        pairs.push((i[key_start..key_end].to_owned(), unescape(value)));
+    }
+
+    apply_labels(pairs)
+}
+
+fn find_unescaped(i: &str, delim: u8) -> Option<usize> {
+    let mut escaped = false;
+    for (idx, b) in i.bytes().enumerate() {
+        match b {
+            _ if escaped => escaped = false,
+            b'\\' => escaped = true,
+            b if b == delim => return Some(idx),
+            _ => (),
+        }
+    }
+
+    None
+}
+
+// The `(start, end)` of each key, where `end` is the index of its `=`
+fn find_keys(i: &str) -> Vec<(usize, usize)> {
+    let bytes = i.as_bytes();
+    let is_key_byte = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || b == b'.' || b == b'-' || b == b'[' || b == b']';
+
+    let mut keys = vec![];
+    let mut start = 0;
+    while start < bytes.len() {
+        if start == 0 || bytes[start - 1] == b' ' {
+            let len = bytes[start..].iter().take_while(|b| is_key_byte(**b)).count();
+            if len > 0 && bytes.get(start + len) == Some(&b'=') {
+                keys.push((start, start + len));
+                start += len + 1;
+                continue;
+            }
+        }
+        start += 1;
+    }
+
+    keys
+}
+
+fn apply_labels(pairs: Vec<(String, String)>) -> Vec<(String, String)> {
+    let label_for = |key: &str| {
+        pairs
+            .iter()
+            .find(|(k, _)| k.len() == key.len() + "Label".len() && k.starts_with(key) && k.ends_with("Label"))
+            .map(|(_, label)| label.clone())
+            .filter(|label| !label.is_empty())
+    };
+
+    let mut labelled = Vec::with_capacity(pairs.len());
+    for (k, v) in &pairs {
+        // Labels are consumed by the fields they name
+        if let Some(field) = k.strip_suffix("Label") {
+            if pairs.iter().any(|(k, _)| k == field) {
+                continue;
+            }
+        }
+
+        match label_for(k) {
+            Some(label) => labelled.push((label, v.clone())),
+            None => labelled.push((k.clone(), v.clone())),
+        }
+    }
+
+    labelled
+}
+
+fn unescape(i: &str) -> String {
+    if !i.contains('\\') {
+        return i.to_owned();
+    }
+
+    let mut unescaped = String::with_capacity(i.len());
+    let mut chars = i.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(c @ '\\') | Some(c @ '|') | Some(c @ '=') => unescaped.push(c),
+            Some(c) => {
+                unescaped.push('\\');
+                unescaped.push(c);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn parses_header_and_extension() {
+        let msg = message("CEF:0|Security|threatmanager|1.0|100|worm successfully stopped|10|src=10.0.0.1 dst=2.1.2.2 spt=1232")
+            .expect("failed to parse message");
+
+        assert_eq!(Message {
+            version: "0".to_owned(),
+            device_vendor: "Security".to_owned(),
+            device_product: "threatmanager".to_owned(),
+            device_version: "1.0".to_owned(),
+            signature_id: "100".to_owned(),
+            name: "worm successfully stopped".to_owned(),
+            severity: "10".to_owned(),
+            extension: pairs(&[("src", "10.0.0.1"), ("dst", "2.1.2.2"), ("spt", "1232")]),
+        }, msg);
+        assert_eq!(Some(2), msg.syslog_severity());
+    }
+
+    #[test]
+    fn parses_escaped_header_fields() {
+        let msg = message(r"CEF:0|security|threatmanager|1.0|100|detected a \| in message|Medium|").expect("failed to parse message");
+
+        assert_eq!("detected a | in message", msg.name);
+        assert_eq!(Some(4), msg.syslog_severity());
+        assert!(msg.extension.is_empty());
+    }
+
+    #[test]
+    fn parses_message_after_tag() {
+        let msg = message("firewall: CEF:1|Vendor|Product|2.0|sig|Name|Low|act=blocked").expect("failed to parse message");
+
+        assert_eq!("1", msg.version);
+        assert_eq!(Some(6), msg.syslog_severity());
+        assert_eq!(pairs(&[("act", "blocked")]), msg.extension);
+    }
+
+    #[test]
+    fn extension_values_may_contain_spaces_and_escapes() {
+        let ext = extension(r"msg=Detected a threat.\nNo action needed a\=b filePath=C:\\Windows\\x cs1=Block cs1Label=Rule Action");
+
+        assert_eq!(pairs(&[
+            ("msg", "Detected a threat.\nNo action needed a=b"),
+            ("filePath", r"C:\Windows\x"),
+            ("Rule Action", "Block"),
+        ]), ext);
+    }
+
+    #[test]
+    fn unused_labels_are_kept() {
+        let ext = extension("cs2Label=Unused act=allow");
+
+        assert_eq!(pairs(&[("cs2Label", "Unused"), ("act", "allow")]), ext);
+    }
+
+    #[test]
+    fn rejects_other_messages() {
+        let cases = [
+            "su: 'su root' failed for lonvick on /dev/pts/8",
+            "CEF:0|too|few|fields",
+            "NOTCEF:0|Vendor|Product|1.0|100|Name|1|",
+            "CEF:|Vendor|Product|1.0|100|Name|1|",
+        ];
+
+        for case in &cases {
+            assert_eq!(None, message(case), "{}", case);
+        }
+    }
+}
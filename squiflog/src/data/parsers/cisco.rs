@@ -0,0 +1,263 @@
+// Cisco IOS, ASA, and NX-OS message bodies, like:
+//
+//   123: *Mar  1 18:46:11.123: %LINK-3-UPDOWN: Interface FastEthernet0/0, changed state to up
+//   %ASA-6-302013: Built outbound TCP connection 1 for outside:192.0.2.1/80
+//   : 2023 Oct 11 22:14:15 UTC: %ETHPORT-5-IF_UP: Interface Ethernet1/1 is up
+//
+// These are what's left over after the RFC3164 header has been parsed, so they may
+// still contain a sequence number, hostname, and timestamp of their own.
+
+use chrono::{DateTime, Utc};
+
+use super::{any_byte, byte, digits, loose_timestamp, until, year_first_timestamp, ParserResult};
+use crate::error::err_msg;
+
+#[derive(Debug, PartialEq)]
+pub struct Message<'a> {
+    pub sequence_number: Option<u64>,
+    pub hostname: Option<&'a str>,
+    pub clock: Option<Clock>,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub unresolved_timestamp: Option<&'a str>,
+    pub facility: &'a str,
+    pub severity: u8,
+    pub mnemonic: &'a str,
+    pub message: &'a str,
+}
+
+/**
+The state of the device clock, as marked before the timestamp.
+*/
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Clock {
+    /**
+    The clock has never been set (`*`).
+    */
+    Unauthoritative,
+    /**
+    The clock was set, but is no longer synchronized (`.`).
+    */
+    Unsynchronized,
+}
+
+impl Clock {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Clock::Unauthoritative => "unauthoritative",
+            Clock::Unsynchronized => "unsynchronized",
+        }
+    }
+}
+
+pub fn message<'a>(i: &'a str, now: &DateTime<Utc>) -> Option<Message<'a>> {
+    // Cheaply rule out messages that can't contain a Cisco mnemonic
+    if !i.contains('%') {
+        return None;
+    }
+
+    let mut rem = skip_separators(i.as_bytes());
+
+    let mut sequence_number = None;
+    if let Ok((seq, seq_rem)) = sequence(rem) {
+        sequence_number = Some(seq);
+        rem = skip_separators(seq_rem);
+    }
+
+    let mut hostname = None;
+    if let Ok((host, host_rem)) = origin(rem) {
+        hostname = Some(host);
+        rem = skip_separators(host_rem);
+    }
+
+    let mut clock = None;
+    match any_byte(rem) {
+        Ok((b'*', clock_rem)) => {
+            clock = Some(Clock::Unauthoritative);
+            rem = clock_rem;
+        }
+        Ok((b'.', clock_rem)) => {
+            clock = Some(Clock::Unsynchronized);
+            rem = clock_rem;
+        }
+        _ => (),
+    }
+
+    let mut timestamp = None;
+    let mut unresolved_timestamp = None;
+    if let Ok((ts, ts_rem)) = year_first_timestamp(rem, now).or_else(|_| loose_timestamp(rem, now)) {
+        let (zone, ts_rem) = zone_name(ts_rem);
+        if let Ok((_, sep_rem)) = byte(ts_rem, b':') {
+            // A timestamp in a zone we can't resolve to an offset would be off by that
+            // offset if read as local time, so it's kept as text instead
+            if zone.is_some() {
+                let text = &rem[..rem.len() - ts_rem.len()];
+                unresolved_timestamp = Some(std::str::from_utf8(text).ok()?);
+            } else {
+                timestamp = Some(ts);
+            }
+            rem = skip_separators(sep_rem);
+        }
+    }
+
+    let ((facility, severity, mnemonic), rem) = mnemonic(rem).ok()?;
+
+    Some(Message {
+        sequence_number,
+        hostname,
+        clock,
+        timestamp,
+        unresolved_timestamp,
+        facility,
+        severity,
+        mnemonic,
+        message: std::str::from_utf8(rem).ok()?.trim(),
+    })
+}
+
+// `123:`
+fn sequence(i: &[u8]) -> ParserResult<'_, u64> {
+    let (seq, rem) = until(i, b':')?;
+    if seq.is_empty() || !seq.iter().all(|b| b.is_ascii_digit()) {
+        return Err(err_msg("invalid sequence number"));
+    }
+    let seq = std::str::from_utf8(seq)?.parse()?;
+    Ok((seq, &rem[1..]))
+}
+
+// `router1:`, as sent with `logging origin-id hostname`
+fn origin(i: &[u8]) -> ParserResult<'_, &str> {
+    let (host, rem) = until(i, b':')?;
+    let is_host = !host.is_empty()
+        && !host.contains(&b' ')
+        && !host.iter().all(|b| b.is_ascii_digit())
+        && !matches!(host[0], b'%' | b'*' | b'.');
+
+    if !is_host {
+        return Err(err_msg("invalid hostname"));
+    }
+    Ok((std::str::from_utf8(host)?, &rem[1..]))
+}
+
+// `%LINK-3-UPDOWN:`
+fn mnemonic(i: &[u8]) -> ParserResult<'_, (&str, u8, &str)> {
+    let (_, rem) = byte(i, b'%')?;
+    let (facility, rem) = until(rem, b'-')?;
+    let (_, rem) = byte(rem, b'-')?;
+    let (severity, rem) = digits(rem, 1, 1)?;
+    let (_, rem) = byte(rem, b'-')?;
+    let (mnemonic, rem) = until(rem, b':')?;
+    let (_, rem) = byte(rem, b':')?;
+
+    let is_name = |name: &[u8]| !name.is_empty() && name.iter().all(|b| b.is_ascii_alphanumeric() || *b == b'_' || *b == b'-');
+    if !is_name(facility) || !is_name(mnemonic) || severity > 7 {
+        return Err(err_msg("invalid mnemonic"));
+    }
+
+    Ok(((std::str::from_utf8(facility)?, severity as u8, std::str::from_utf8(mnemonic)?), rem))
+}
+
+// ` PST`, a zone name that couldn't be resolved to an offset
+fn zone_name(i: &[u8]) -> (Option<&[u8]>, &[u8]) {
+    if let Ok((_, rem)) = byte(i, b' ') {
+        let len = rem.iter().take_while(|b| b.is_ascii_uppercase()).count();
+        if (2..=5).contains(&len) {
+            return (Some(&rem[..len]), &rem[len..]);
+        }
+    }
+    (None, i)
+}
+
+fn skip_separators(mut i: &[u8]) -> &[u8] {
+    while let Ok((b, rem)) = any_byte(i) {
+        if b != b' ' && b != b':' {
+            break;
+        }
+        i = rem;
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Timelike};
+
+    #[test]
+    fn parses_ios_message() {
+        let now = Utc.ymd(2023, 3, 2).and_hms(0, 0, 0);
+        let msg = message("123: *Mar  1 18:46:11.123: %LINK-3-UPDOWN: Interface FastEthernet0/0, changed state to up", &now)
+            .expect("failed to parse message");
+
+        assert_eq!(Some(123), msg.sequence_number);
+        assert_eq!(None, msg.hostname);
+        assert_eq!(Some(Clock::Unauthoritative), msg.clock);
+        assert_eq!(123_000_000, msg.timestamp.expect("missing timestamp").nanosecond());
+        assert_eq!("LINK", msg.facility);
+        assert_eq!(3, msg.severity);
+        assert_eq!("UPDOWN", msg.mnemonic);
+        assert_eq!("Interface FastEthernet0/0, changed state to up", msg.message);
+    }
+
+    #[test]
+    fn parses_ios_message_with_origin_hostname() {
+        let now = Utc.ymd(2023, 3, 2).and_hms(0, 0, 0);
+        let msg = message("42: router1: .Mar  1 18:46:11 UTC: %SYS-5-CONFIG_I: Configured from console", &now)
+            .expect("failed to parse message");
+
+        assert_eq!(Some(42), msg.sequence_number);
+        assert_eq!(Some("router1"), msg.hostname);
+        assert_eq!(Some(Clock::Unsynchronized), msg.clock);
+        assert_eq!(Some(Utc.ymd(2023, 3, 1).and_hms(18, 46, 11)), msg.timestamp);
+        assert_eq!("SYS", msg.facility);
+        assert_eq!(5, msg.severity);
+        assert_eq!("CONFIG_I", msg.mnemonic);
+    }
+
+    #[test]
+    fn parses_asa_message() {
+        let msg = message(": %ASA-6-302013: Built outbound TCP connection 1", &Utc::now()).expect("failed to parse message");
+
+        assert_eq!(None, msg.sequence_number);
+        assert_eq!(None, msg.timestamp);
+        assert_eq!("ASA", msg.facility);
+        assert_eq!(6, msg.severity);
+        assert_eq!("302013", msg.mnemonic);
+        assert_eq!("Built outbound TCP connection 1", msg.message);
+    }
+
+    #[test]
+    fn parses_nxos_message() {
+        let msg = message(": 2022 Oct 11 22:14:15 UTC: %ETHPORT-5-IF_UP: Interface Ethernet1/1 is up", &Utc::now())
+            .expect("failed to parse message");
+
+        assert_eq!(Some(Utc.ymd(2022, 10, 11).and_hms(22, 14, 15)), msg.timestamp);
+        assert_eq!("ETHPORT", msg.facility);
+        assert_eq!("IF_UP", msg.mnemonic);
+    }
+
+    #[test]
+    fn keeps_timestamps_with_unresolved_zones_as_text() {
+        let msg = message("7: *Mar  1 18:46:11.123 PST: %LINK-3-UPDOWN: Interface up", &Utc::now()).expect("failed to parse message");
+
+        assert_eq!(None, msg.timestamp);
+        assert_eq!(Some("Mar  1 18:46:11.123 PST"), msg.unresolved_timestamp);
+        assert_eq!(Some(Clock::Unauthoritative), msg.clock);
+        assert_eq!("UPDOWN", msg.mnemonic);
+        assert_eq!("Interface up", msg.message);
+    }
+
+    #[test]
+    fn rejects_other_messages() {
+        let cases = [
+            "su: 'su root' failed for lonvick on /dev/pts/8",
+            "disk is 95% full",
+            "123: %LINK-9-UPDOWN: invalid severity",
+            "%LINK UPDOWN: missing separators",
+            "%-3-UPDOWN: missing facility",
+        ];
+
+        for case in &cases {
+            assert_eq!(None, message(case, &Utc::now()), "{}", case);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+// `key=value` pairs in message bodies, like those sent by Fortinet and MikroTik devices,
+// or logfmt-style application logs:
+//
+//   date=2019-05-10 time=11:37:47 logid="0000000013" type="traffic" msg="allowed \"ok\""
+//
+// Values may be double-quoted, in which case they can contain spaces and `\"` or `\\` escapes.
+// Any text that isn't a `key=value` pair is ignored.
+
+pub fn pairs(i: &str) -> Vec<(&str, String)> {
+    let mut pairs = vec![];
+    let mut rem = i;
+
+    loop {
+        rem = rem.trim_start();
+        if rem.is_empty() {
+            break;
+        }
+
+        match pair(rem) {
+            Some((pair, pair_rem)) => {
+                pairs.push(pair);
+                rem = pair_rem;
+            }
+            // Skip over any text that isn't a pair
+            None => rem = skip_token(rem),
+        }
+    }
+
+    pairs
+}
+
+fn pair(i: &str) -> Option<((&str, String), &str)> {
+    let key_len = i.find(|c: char| c == '=' || c == '"' || c.is_whitespace()).unwrap_or(i.len());
+    if key_len == 0 || !i[key_len..].starts_with('=') {
+        return None;
+    }
+
+    let key = &i[..key_len];
+    let rem = &i[key_len + 1..];
+
+    let (value, rem) = if let Some(quoted) = rem.strip_prefix('"') {
+        quoted_value(quoted)?
+    } else {
+        let end = rem.find(char::is_whitespace).unwrap_or(rem.len());
+        (rem[..end].to_owned(), &rem[end..])
+    };
+
+    Some(((key, value), rem))
+}
+
+// The content of a double-quoted value, following the opening quote
+fn quoted_value(i: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = i.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &i[idx + 1..])),
+            '\\' => match chars.next() {
+                Some((_, c @ '"')) | Some((_, c @ '\\')) => value.push(c),
+                Some((_, c)) => {
+                    value.push('\\');
+                    value.push(c);
+                }
+                None => value.push('\\'),
+            },
+            c => value.push(c),
+        }
+    }
+
+    // The value was never closed
+    None
+}
+
+// Skip a token that isn't a pair, including any quoted text in it
+fn skip_token(i: &str) -> &str {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (idx, c) in i.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => return &i[idx..],
+            _ => (),
+        }
+    }
+
+    ""
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owned<'a>(pairs: &[(&'a str, &str)]) -> Vec<(&'a str, String)> {
+        pairs.iter().map(|(k, v)| (*k, v.to_string())).collect()
+    }
+
+    #[test]
+    fn parses_fortinet_pairs() {
+        let actual = pairs(r#"date=2019-05-10 time=11:37:47 logid="0000000013" type="traffic" msg="session \"allowed\"""#);
+
+        assert_eq!(owned(&[
+            ("date", "2019-05-10"),
+            ("time", "11:37:47"),
+            ("logid", "0000000013"),
+            ("type", "traffic"),
+            ("msg", r#"session "allowed""#),
+        ]), actual);
+    }
+
+    #[test]
+    fn parses_logfmt_pairs() {
+        let actual = pairs(r#"level=info msg="request completed" path=/api/events status=200 duration=1.5ms empty="#);
+
+        assert_eq!(owned(&[
+            ("level", "info"),
+            ("msg", "request completed"),
+            ("path", "/api/events"),
+            ("status", "200"),
+            ("duration", "1.5ms"),
+            ("empty", ""),
+        ]), actual);
+    }
+
+    #[test]
+    fn ignores_text_between_pairs() {
+        let actual = pairs(r#"firewall,info input: in:ether1 out:(unknown 0) src-mac 00:11:22:33:44:55, proto=TCP "quoted = text" len=60"#);
+
+        assert_eq!(owned(&[("proto", "TCP"), ("len", "60")]), actual);
+    }
+
+    #[test]
+    fn keeps_unknown_escapes() {
+        let actual = pairs(r#"path="C:\Windows\\System32""#);
+
+        assert_eq!(owned(&[("path", r"C:\Windows\System32")]), actual);
+    }
+
+    #[test]
+    fn ignores_unclosed_quotes() {
+        let actual = pairs(r#"a=1 b="unclosed c=3"#);
+
+        assert_eq!(owned(&[("a", "1")]), actual);
+    }
+
+    #[test]
+    fn finds_no_pairs_in_plain_text() {
+        assert!(pairs("su: 'su root' failed for lonvick on /dev/pts/8").is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+// IBM QRadar Log Event Extended Format (LEEF) message bodies, like:
+//
+//   LEEF:1.0|Microsoft|MSExchange|4.0 SP1|15345|src=192.0.2.0	dst=172.50.123.1	sev=5
+//   LEEF:2.0|Lancope|StealthWatch|1.0|41|^|src=192.0.2.0^dst=172.50.123.1^sev=5
+//
+// LEEF 1.0 attributes are separated by tabs. LEEF 2.0 adds an optional header field
+// with the attribute delimiter, either as a character or its hex code, like `x5E`.
+
+use super::find_format_prefix;
+
+#[derive(Debug, PartialEq)]
+pub struct Message<'a> {
+    pub version: &'a str,
+    pub vendor: &'a str,
+    pub product: &'a str,
+    pub product_version: &'a str,
+    pub event_id: &'a str,
+    pub attributes: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Message<'a> {
+    /**
+    The equivalent SYSLOG severity for the LEEF `sev` attribute, from `1` to `10`.
+    */
+    pub fn syslog_severity(&self) -> Option<u8> {
+        let (_, sev) = self.attributes.iter().find(|(k, _)| *k == "sev")?;
+
+        match sev.trim().parse::<u8>().ok()? {
+            0..=3 => Some(6),
+            4..=6 => Some(4),
+            7..=8 => Some(3),
+            9..=10 => Some(2),
+            _ => None,
+        }
+    }
+}
+
+pub fn message(i: &str) -> Option<Message<'_>> {
+    let start = find_format_prefix(i, "LEEF:")?;
+    let mut rem = &i[start + "LEEF:".len()..];
+
+    let mut fields = [""; 5];
+    for field in fields.iter_mut() {
+        let end = rem.find('|')?;
+        *field = &rem[..end];
+        rem = &rem[end + 1..];
+    }
+
+    let [version, vendor, product, product_version, event_id] = fields;
+
+    let mut delimiter = '\t';
+    if version.starts_with('2') {
+        if let Some(end) = rem.find('|') {
+            if let Some(custom) = parse_delimiter(&rem[..end]) {
+                delimiter = custom;
+                rem = &rem[end + 1..];
+            }
+        }
+    }
+
+    Some(Message {
+        version,
+        vendor,
+        product,
+        product_version,
+        event_id,
+        attributes: attributes(rem, delimiter),
+    })
+}
+
+fn attributes(i: &str, delimiter: char) -> Vec<(&str, &str)> {
+    i.trim_end_matches(&['\r', '\n'][..])
+        .split(delimiter)
+        .filter_map(|attr| {
+            let eq = attr.find('=')?;
+            let k = attr[..eq].trim();
+            if k.is_empty() {
+                None
+            } else {
+                Some((k, &attr[eq + 1..]))
+            }
+        })
+        .collect()
+}
+
+// The LEEF 2.0 delimiter field, like `^`, `x5E`, or `0x5E`
+fn parse_delimiter(field: &str) -> Option<char> {
+    let mut chars = field.chars();
+    match (chars.next(), chars.next()) {
+        // An empty field means the default
+        (None, _) => Some('\t'),
+        (Some(c), None) if c != '=' => Some(c),
+        _ => {
+            let hex = field.strip_prefix("0x").or_else(|| field.strip_prefix('x'))?;
+            if hex.is_empty() || hex.len() > 4 {
+                return None;
+            }
+            std::char::from_u32(u32::from_str_radix(hex, 16).ok()?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_leef_1() {
+        let msg = message("LEEF:1.0|Microsoft|MSExchange|4.0 SP1|15345|src=192.0.2.0\tdst=172.50.123.1\tsev=5\tmsg=a message")
+            .expect("failed to parse message");
+
+        assert_eq!(Message {
+            version: "1.0",
+            vendor: "Microsoft",
+            product: "MSExchange",
+            product_version: "4.0 SP1",
+            event_id: "15345",
+            attributes: vec![("src", "192.0.2.0"), ("dst", "172.50.123.1"), ("sev", "5"), ("msg", "a message")],
+        }, msg);
+        assert_eq!(Some(4), msg.syslog_severity());
+    }
+
+    #[test]
+    fn parses_leef_2_with_delimiter() {
+        let msg = message("LEEF:2.0|Lancope|StealthWatch|1.0|41|^|src=192.0.2.0^dst=172.50.123.1^sev=9").expect("failed to parse message");
+
+        assert_eq!(vec![("src", "192.0.2.0"), ("dst", "172.50.123.1"), ("sev", "9")], msg.attributes);
+        assert_eq!(Some(2), msg.syslog_severity());
+    }
+
+    #[test]
+    fn parses_leef_2_with_hex_delimiter() {
+        for delimiter in &["x5E", "0x5E", "x5e"] {
+            let input = format!("LEEF:2.0|Lancope|StealthWatch|1.0|41|{}|src=192.0.2.0^dst=172.50.123.1", delimiter);
+            let msg = message(&input).expect("failed to parse message");
+
+            assert_eq!(vec![("src", "192.0.2.0"), ("dst", "172.50.123.1")], msg.attributes);
+        }
+    }
+
+    #[test]
+    fn parses_leef_2_without_delimiter() {
+        let msg = message("LEEF:2.0|Lancope|StealthWatch|1.0|41|src=192.0.2.0\tdst=172.50.123.1").expect("failed to parse message");
+
+        assert_eq!(vec![("src", "192.0.2.0"), ("dst", "172.50.123.1")], msg.attributes);
+    }
+
+    #[test]
+    fn parses_message_after_tag() {
+        let msg = message("qradar: LEEF:1.0|Vendor|Product|1.0|login|usrName=bob").expect("failed to parse message");
+
+        assert_eq!("login", msg.event_id);
+        assert_eq!(vec![("usrName", "bob")], msg.attributes);
+    }
+
+    #[test]
+    fn rejects_other_messages() {
+        let cases = [
+            "su: 'su root' failed for lonvick on /dev/pts/8",
+            "LEEF:1.0|too|few",
+            "LEEF:|Vendor|Product|1.0|41|src=192.0.2.0",
+        ];
+
+        for case in &cases {
+            assert_eq!(None, message(case), "{}", case);
+        }
+    }
+}
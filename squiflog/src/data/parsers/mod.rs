@@ -0,0 +1,632 @@
+use crate::error::{Error, err_msg};
+use chrono::{Utc, DateTime, Local, Datelike, TimeZone, FixedOffset, NaiveDate};
+use crate::data::syslog::StructuredDataElement;
+
+pub mod access;
+pub mod auditd;
+pub mod cee;
+pub mod cef;
+pub mod cisco;
+pub mod kv;
+pub mod leef;
+
+type ParserResult<'a, T> = Result<(T, &'a [u8]), Error>;
+
+pub fn priority(i: &[u8]) -> ParserResult<'_, u8> {
+    let (content, rem) = delimited(i, b'<', b'>')?;
+    if content.is_empty() || content.iter().any(|b| !char::is_digit(*b as char, 10)) {
+        return Err(err_msg("invalid priority content"));
+    }
+    let pval = std::str::from_utf8(content)?.parse::<u8>()?;
+    Ok((pval, rem))
+}
+
+pub fn any_byte(i: &[u8]) -> ParserResult<'_, u8> {
+    if i.is_empty() {
+        Err(err_msg("unexpected end of input"))
+    } else {
+        Ok((i[0], &i[1..]))
+    }
+}
+
+pub fn byte(i: &[u8], b: u8) -> ParserResult<'_, ()> {
+    if let Ok((actual, rem)) = any_byte(i) {
+        if actual == b {
+            Ok(((), rem))
+        } else {
+            Err(err_msg("unexpected byte"))
+        }
+    } else {
+        Err(err_msg("expected byte, unexpected end of input"))
+    }
+}
+
+pub fn until(i: &[u8], end: u8) -> ParserResult<'_, &[u8]> {
+    let mut rem = i;
+    let mut count = 0;
+    while !rem.is_empty() {
+        if rem[0] == end {
+            return Ok((&i[0..count], rem));
+        }
+        rem = &rem[1..];
+        count += 1;
+    }
+
+    Err(err_msg(format!("missing end `{}` delimiter", end as char)))
+}
+
+pub fn delimited(i: &[u8], start: u8, end: u8) -> ParserResult<'_, &[u8]> {
+    let rem = i;
+    if rem.is_empty() || rem[0] != start {
+        return Err(err_msg("missing start delimiter"));
+    }
+
+    let rem = &rem[1..];
+    if rem.is_empty() {
+        return Err(err_msg("missing delimited content"));
+    }
+
+    let (content, rem) = until(rem, end)?;
+
+    Ok((content, &rem[1..]))
+}
+
+pub fn take(i: &[u8], count: usize) -> ParserResult<'_, &[u8]> {
+    if i.len() < count {
+        return Err(err_msg("the input is too short"));
+    }
+
+    Ok((&i[..count], &i[count..]))
+}
+
+pub fn iso8601_timestamp(i: &[u8]) -> ParserResult<'_, DateTime<Utc>> {
+    let (to_space, rem) = until(i, b' ')?; // Cheating a little here; we shouldn't need any trailing delimiter
+    let maybe_ts = std::str::from_utf8(to_space)?;
+    let utc = DateTime::parse_from_rfc3339(maybe_ts)?.with_timezone(&Utc);
+    Ok((utc, rem))
+}
+
+// Accepts RFC3339 timestamps, or BSD-style `%h %d %H:%M:%S` timestamps with
+// the common vendor extensions: fractional seconds, a year before the time,
+// and a trailing `UTC`/`GMT`/`Z`/numeric offset. Timestamps without a year
+// are placed in the year nearest `now`; those without a zone are local.
+pub fn loose_timestamp<'a>(i: &'a [u8], now: &DateTime<Utc>) -> ParserResult<'a, DateTime<Utc>> {
+    if let Ok((iso_ts, rem)) = iso8601_timestamp(i) {
+        return Ok((iso_ts, rem));
+    }
+
+    bsd_timestamp(i, None, now)
+}
+
+// A `%Y %h %d %H:%M:%S` timestamp, as used by Cisco NX-OS
+pub fn year_first_timestamp<'a>(i: &'a [u8], now: &DateTime<Utc>) -> ParserResult<'a, DateTime<Utc>> {
+    let (year, rem) = digits(i, 4, 4)?;
+    let (_, rem) = byte(rem, b' ')?;
+
+    bsd_timestamp(rem, Some(year as i32), now)
+}
+
+fn bsd_timestamp<'a>(i: &'a [u8], known_year: Option<i32>, now: &DateTime<Utc>) -> ParserResult<'a, DateTime<Utc>> {
+    let (month, rem) = month(i)?;
+    let (_, rem) = spaces(rem)?;
+    let (day, rem) = digits(rem, 1, 2)?;
+    let (_, rem) = byte(rem, b' ')?;
+
+    let (year, rem) = match digits(rem, 4, 4) {
+        Ok((year, year_rem)) => {
+            let (_, year_rem) = byte(year_rem, b' ')?;
+            (Some(year as i32), year_rem)
+        }
+        Err(_) => (None, rem),
+    };
+
+    let (hour, rem) = digits(rem, 2, 2)?;
+    let (_, rem) = byte(rem, b':')?;
+    let (minute, rem) = digits(rem, 2, 2)?;
+    let (_, rem) = byte(rem, b':')?;
+    let (second, mut rem) = digits(rem, 2, 2)?;
+
+    let mut nano = 0;
+    if let Ok((_, frac_rem)) = byte(rem, b'.') {
+        let (frac, frac_rem) = fraction_nanos(frac_rem)?;
+        nano = frac;
+        rem = frac_rem;
+    }
+
+    let mut offset = None;
+    if let Ok((_, sp_rem)) = byte(rem, b' ') {
+        if let Ok((tz, tz_rem)) = timezone(sp_rem) {
+            offset = Some(tz);
+            rem = tz_rem;
+        }
+    }
+
+    let year = known_year.or(year).unwrap_or_else(|| {
+        let year_offset = if month == 12 && now.month() == 1 {
+            -1
+        } else if month == 1 && now.month() == 12 {
+            1
+        } else {
+            0
+        };
+
+        now.year() + year_offset
+    });
+
+    let naive = NaiveDate::from_ymd_opt(year, month, day)
+        .and_then(|date| date.and_hms_nano_opt(hour, minute, second, nano))
+        .ok_or_else(|| err_msg("invalid timestamp"))?;
+
+    let utc = match offset {
+        Some(offset) => offset.from_local_datetime(&naive).single().map(|ts| ts.with_timezone(&Utc)),
+        None => Local.from_local_datetime(&naive).earliest().map(|ts| ts.with_timezone(&Utc)),
+    };
+
+    Ok((utc.ok_or_else(|| err_msg("invalid local timestamp"))?, rem))
+}
+
+pub fn month(i: &[u8]) -> ParserResult<'_, u32> {
+    let (name, rem) = take(i, 3)?;
+    let month = match name {
+        b"Jan" => 1,
+        b"Feb" => 2,
+        b"Mar" => 3,
+        b"Apr" => 4,
+        b"May" => 5,
+        b"Jun" => 6,
+        b"Jul" => 7,
+        b"Aug" => 8,
+        b"Sep" => 9,
+        b"Oct" => 10,
+        b"Nov" => 11,
+        b"Dec" => 12,
+        _ => return Err(err_msg("invalid month name")),
+    };
+    Ok((month, rem))
+}
+
+// Consumes one or more spaces, as used to pad single-digit days
+pub fn spaces(i: &[u8]) -> ParserResult<'_, ()> {
+    let (_, mut rem) = byte(i, b' ')?;
+    while let Ok((_, sp_rem)) = byte(rem, b' ') {
+        rem = sp_rem;
+    }
+    Ok(((), rem))
+}
+
+pub fn digits(i: &[u8], min: usize, max: usize) -> ParserResult<'_, u32> {
+    let count = i.iter().take(max).take_while(|b| b.is_ascii_digit()).count();
+    if count < min {
+        return Err(err_msg("expected digits"));
+    }
+    let value = std::str::from_utf8(&i[..count])?.parse::<u32>()?;
+    Ok((value, &i[count..]))
+}
+
+// Fractional seconds, truncated to nanosecond precision
+pub fn fraction_nanos(i: &[u8]) -> ParserResult<'_, u32> {
+    let count = i.iter().take_while(|b| b.is_ascii_digit()).count();
+    if count == 0 {
+        return Err(err_msg("expected fractional seconds"));
+    }
+    let mut nanos = 0;
+    for (idx, b) in i[..count].iter().take(9).enumerate() {
+        nanos += (b - b'0') as u32 * 10u32.pow(8 - idx as u32);
+    }
+    Ok((nanos, &i[count..]))
+}
+
+// A zone designator trailing a BSD timestamp, as `UTC`, `GMT`, `Z` or `+hh:mm`/`+hhmm`
+pub fn timezone(i: &[u8]) -> ParserResult<'_, FixedOffset> {
+    for name in &[&b"UTC"[..], &b"GMT"[..], &b"Z"[..]] {
+        if i.starts_with(name) && is_token_end(&i[name.len()..]) {
+            return Ok((FixedOffset::east(0), &i[name.len()..]));
+        }
+    }
+
+    let (sign, rem) = any_byte(i)?;
+    let sign = match sign {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return Err(err_msg("invalid timezone")),
+    };
+    let (hours, rem) = digits(rem, 2, 2)?;
+    let rem = byte(rem, b':').map(|(_, rem)| rem).unwrap_or(rem);
+    let (minutes, rem) = digits(rem, 2, 2)?;
+
+    if hours > 23 || minutes > 59 || !is_token_end(rem) {
+        return Err(err_msg("invalid timezone"));
+    }
+
+    let offset = FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60) as i32)
+        .ok_or_else(|| err_msg("invalid timezone"))?;
+    Ok((offset, rem))
+}
+
+fn is_token_end(i: &[u8]) -> bool {
+    i.is_empty() || i[0] == b' ' || i[0] == b':'
+}
+
+// Consumes (requires) a trailing space
+pub fn header_item<'a>(i: &'a [u8], name: &'static str) -> ParserResult<'a, Option<&'a str>> {
+    let (content, rem) = until(i, b' ').map_err(|_| err_msg(format!("missing {}", name)))?;
+    let (_, rem) = byte(rem, b' ')?;
+    if content == &b"-"[..] {
+        Ok((None, rem))
+    } else {
+        Ok((Some(std::str::from_utf8(content)?), rem))
+    }
+}
+
+// An RFC3164 tag before the message text, like `app[42]: `, with its optional process id
+pub fn tag(i: &[u8]) -> ParserResult<'_, (&str, Option<&str>)> {
+    let name_len = i.iter().take_while(|b| b.is_ascii_alphanumeric() || b"_-./".contains(b)).count();
+    if name_len == 0 || !i[0].is_ascii_alphabetic() {
+        return Err(err_msg("missing tag"));
+    }
+
+    let (name, mut rem) = i.split_at(name_len);
+
+    let mut proc_id = None;
+    if let Ok((content, pid_rem)) = delimited(rem, b'[', b']') {
+        proc_id = Some(std::str::from_utf8(content)?);
+        rem = pid_rem;
+    }
+
+    let (_, rem) = byte(rem, b':')?;
+    let rem = match byte(rem, b' ') {
+        Ok((_, rem)) => rem,
+        Err(_) if rem.is_empty() => rem,
+        Err(err) => return Err(err),
+    };
+
+    Ok(((std::str::from_utf8(name)?, proc_id), rem))
+}
+
+pub fn param_value_content_char(i: &[u8]) -> ParserResult<'_, u8> {
+    let (b, rem) = any_byte(i)?;
+    if b == b'"' {
+        Err(err_msg("no param value content char found"))
+    } else if b == b'\\' {
+        let (next, following_rem) = any_byte(rem)?;
+        if next == b'\\' || next == b'\"' || next == b']' {
+            Ok((next, following_rem))
+        } else {
+            Ok((b, rem))
+        }
+    } else {
+        Ok((b, rem))
+    }
+}
+
+pub fn structured_data_element(i: &[u8]) -> ParserResult<'_, StructuredDataElement<'_>> {
+    let (_, rem) = byte(i, b'[')?;
+    let (id, mut rem) = sd_name(rem)?;
+
+    let mut params = vec![];
+    while let Ok((_, sp_rem)) = byte(rem, b' ') {
+        let (param, param_rem) = param(sp_rem)?;
+        params.push(param);
+        rem = param_rem;
+    }
+
+    let (_, rem) = byte(rem, b']')?;
+    Ok((StructuredDataElement{id, params}, rem))
+}
+
+pub fn param_value_content(i: &[u8]) -> ParserResult<'_, String> {
+    let mut bytes = vec![];
+    let mut rem = i;
+    let mut maybe_content = param_value_content_char(rem);
+    while let Ok((b, rest)) = maybe_content {
+        bytes.push(b);
+        rem = rest;
+        maybe_content = param_value_content_char(rem);
+    }
+    Ok((std::str::from_utf8(&bytes[..])?.into(), rem))
+}
+
+pub fn param_value(i: &[u8]) -> ParserResult<'_, String> {
+    let (_, rem) = byte(i, b'"')?;
+    let (content, rem) = param_value_content(rem)?;
+    let (_, rem) = byte(rem, b'"')?;
+    Ok((content, rem))
+}
+
+pub fn sd_name(i: &[u8]) -> ParserResult<'_, &str> {
+    let disallowed: &[u8] = &b"\" ]="[..];
+    let mut rem = i;
+    let mut count = 0;
+    let mut maybe_char = any_byte(rem);
+    while let Ok((b, rest)) = maybe_char {
+        if disallowed.contains(&b) {
+            break;
+        }
+        rem = rest;
+        count += 1;
+        maybe_char = any_byte(rem);
+    }
+    if count == 0 {
+        Err(err_msg("missing param name"))
+    } else {
+        Ok((std::str::from_utf8(&i[..count])?, rem))
+    }
+}
+
+pub fn param(i: &[u8]) -> ParserResult<'_, (&str, String)> {
+    let (name, rem) = sd_name(i)?;
+    let (_, rem) = byte(rem, b'=')?;
+    let (value, rem) = param_value(rem)?;
+    Ok(((name, value), rem))
+}
+
+/**
+Find a prefix, like `@cee:`, at the start of a message or directly after its RFC3164 tag,
+like `app[42]: @cee:`.
+*/
+pub fn find_prefix(i: &str, prefix: &str) -> Option<usize> {
+    if i.starts_with(prefix) {
+        return Some(0);
+    }
+
+    let (_, rem) = tag(i.as_bytes()).ok()?;
+    let start = i.len() - rem.len();
+    if i[start..].starts_with(prefix) {
+        Some(start)
+    } else {
+        None
+    }
+}
+
+/**
+Find a format prefix followed by a version, like `CEF:0`, at the start of a message or
+directly after its RFC3164 tag, like `app: CEF:0`.
+*/
+pub fn find_format_prefix(i: &str, prefix: &str) -> Option<usize> {
+    let start = find_prefix(i, prefix)?;
+    let has_version = i.as_bytes().get(start + prefix.len()).map(|b| b.is_ascii_digit()).unwrap_or(false);
+
+    if has_version {
+        Some(start)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+#[allow(non_fmt_panics)]
+mod tests {
+    use super::*;
+    use chrono::Timelike;
+
+    #[test]
+    fn delimited_accepts_valid_content() {
+        let c = b"(hello, world) and then";
+        let (content, rem) = delimited(c, b'(', b')').expect("failed to parse delimiters");
+        assert_eq!(b"hello, world", content);
+        assert_eq!(b" and then", rem);
+    }
+
+    #[test]
+    fn tag_accepts_names_and_process_ids() {
+        let (parsed, rem) = tag(b"sshd[42]: Accepted publickey").expect("failed to parse tag");
+        assert_eq!(("sshd", Some("42")), parsed);
+        assert_eq!(b"Accepted publickey", rem);
+
+        let (parsed, rem) = tag(b"kernel: eth0 up").expect("failed to parse tag");
+        assert_eq!(("kernel", None), parsed);
+        assert_eq!(b"eth0 up", rem);
+    }
+
+    #[test]
+    fn tag_rejects_other_text() {
+        let cases = [&b"Request failed"[..], &b"[42]: text"[..], &b"12:34:56 text"[..], &b"app[42] text"[..], &b"app:text"[..], &b""[..]].to_vec();
+        for case in cases {
+            assert!(tag(case).is_err(), "{:?}", case);
+        }
+    }
+
+    #[test]
+    fn format_prefix_is_found_at_start_or_after_tag() {
+        assert_eq!(Some(0), find_format_prefix("CEF:0|Vendor", "CEF:"));
+        assert_eq!(Some(8), find_format_prefix("fw[42]: CEF:0|Vendor", "CEF:"));
+        assert_eq!(Some(8), find_format_prefix("qradar: LEEF:2.0|Vendor", "LEEF:"));
+    }
+
+    #[test]
+    fn format_prefix_is_not_found_elsewhere() {
+        let cases = [
+            "user said CEF:0|Vendor",
+            "fw[42]: forwarded CEF:0|Vendor",
+            "fw[42]:CEF:0|Vendor",
+            "CEF:|Vendor",
+            "xCEF:0|Vendor",
+        ];
+
+        for case in &cases {
+            assert_eq!(None, find_format_prefix(case, "CEF:"), "{}", case);
+        }
+    }
+
+    #[test]
+    fn delimited_rejects_invalid_content() {
+        let cases = [&b"(test"[..], &b"test)"[..], &b" "[..], &b""[..], &b"("[..], &b")"[..]].to_vec();
+        for case in cases {
+            let expect_err = delimited(case, b'(', b')');
+            assert!(expect_err.is_err(), case);
+        }
+    }
+
+    #[test]
+    fn parses_loose_timestamps() {
+        let ts = b"Oct 28 12:34:56";
+        loose_timestamp(ts, &Utc::now()).expect("could not parse timestamp");
+    }
+
+    #[test]
+    fn parses_loose_timestamps_with_padded_day() {
+        let now = Utc.ymd(2020, 3, 2).and_hms(0, 0, 0);
+        let (ts, rem) = loose_timestamp(b"Mar  1 18:46:11 host", &now).expect("could not parse timestamp");
+        assert_eq!(1, ts.with_timezone(&Local).day());
+        assert_eq!(b" host", rem);
+    }
+
+    #[test]
+    fn parses_loose_timestamps_with_fractional_seconds() {
+        let now = Utc.ymd(2020, 10, 11).and_hms(0, 0, 0);
+        let (ts, rem) = loose_timestamp(b"Oct 11 22:14:15.123456 mymachine", &now).expect("could not parse timestamp");
+        assert_eq!(123_456_000, ts.nanosecond());
+        assert_eq!(b" mymachine", rem);
+    }
+
+    #[test]
+    fn parses_loose_timestamps_with_year() {
+        let now = Utc.ymd(2024, 1, 1).and_hms(0, 0, 0);
+        let (ts, rem) = loose_timestamp(b"Oct 11 2023 22:14:15 mymachine", &now).expect("could not parse timestamp");
+        assert_eq!(2023, ts.with_timezone(&Local).year());
+        assert_eq!(b" mymachine", rem);
+    }
+
+    #[test]
+    fn parses_loose_timestamps_with_timezone() {
+        let now = Utc.ymd(2023, 10, 11).and_hms(0, 0, 0);
+        let cases: &[(&[u8], DateTime<Utc>)] = &[
+            (b"Oct 11 22:14:15 UTC mymachine", Utc.ymd(2023, 10, 11).and_hms(22, 14, 15)),
+            (b"Oct 11 22:14:15.003 GMT mymachine", Utc.ymd(2023, 10, 11).and_hms_milli(22, 14, 15, 3)),
+            (b"Oct 11 2022 22:14:15 +02:00 mymachine", Utc.ymd(2022, 10, 11).and_hms(20, 14, 15)),
+            (b"Oct 11 22:14:15 -0130 mymachine", Utc.ymd(2023, 10, 11).and_hms(23, 44, 15)),
+        ];
+
+        for (input, expected) in cases {
+            let (ts, rem) = loose_timestamp(input, &now).expect("could not parse timestamp");
+            assert_eq!(*expected, ts);
+            assert_eq!(b" mymachine", rem);
+        }
+    }
+
+    #[test]
+    fn loose_timestamp_timezone_must_be_a_whole_token() {
+        let now = Utc.ymd(2023, 10, 11).and_hms(0, 0, 0);
+        let (_, rem) = loose_timestamp(b"Oct 11 22:14:15 Zeus", &now).expect("could not parse timestamp");
+        assert_eq!(b" Zeus", rem);
+    }
+
+    #[test]
+    fn loose_timestamps_without_year_roll_over() {
+        let january = Utc.ymd(2021, 1, 1).and_hms(0, 30, 0);
+        let (ts, _) = loose_timestamp(b"Dec 31 23:59:59.5", &january).expect("could not parse timestamp");
+        assert_eq!(2020, ts.with_timezone(&Local).year());
+
+        let december = Utc.ymd(2020, 12, 31).and_hms(23, 30, 0);
+        let (ts, _) = loose_timestamp(b"Jan  1 00:00:01", &december).expect("could not parse timestamp");
+        assert_eq!(2021, ts.with_timezone(&Local).year());
+
+        let (ts, _) = loose_timestamp(b"Dec 31 2019 23:59:59", &january).expect("could not parse timestamp");
+        assert_eq!(2019, ts.with_timezone(&Local).year());
+    }
+
+    #[test]
+    fn loose_timestamps_reject_invalid_input() {
+        let now = Utc::now();
+        let cases = [&b"Foo 11 22:14:15"[..], &b"Oct 11 22:14"[..], &b"Oct 32 22:14:15"[..], &b"Oct 11 22:14:15."[..]];
+        for case in &cases {
+            assert!(loose_timestamp(case, &now).is_err());
+        }
+    }
+
+    #[test]
+    fn parses_iso8601_timestamps() {
+        let ts = b"1985-04-12T23:20:50.52Z "; // Note end delimiter
+        iso8601_timestamp(ts).expect("could not parse timestamp");
+    }
+
+    #[test]
+    fn parses_tight_timestamps() {
+        let ts = b"1985-04-12T23:20:50.52Z "; // Note end delimiter
+        loose_timestamp(ts, &Utc::now()).expect("could not parse timestamp");
+    }
+
+    #[test]
+    fn until_excludes_end() {
+        let i = b"12345";
+        let (one_two, _) = until(i, b'3').expect("could not parse items");
+        assert_eq!(&b"12"[..], one_two);
+    }
+
+    #[test]
+    fn double_quotes_escaped_in_param_values() {
+        let i = b"\\\"test";
+        let (b, _) = param_value_content_char(i).expect("parser failed");
+        assert_eq!(b'"' as char, b as char);
+    }
+
+    #[test]
+    fn normal_chars_as_is_in_param_values() {
+        let i = b"test";
+        let (b, _) = param_value_content_char(i).expect("parser failed");
+        assert_eq!(b't', b);
+    }
+
+    #[test]
+    fn param_value_content_excludes_closing_quotes() {
+        let i = b"text\\\"stuff\" and more";
+        let (s, _) = param_value_content(i).expect("parser failed");
+        assert_eq!("text\"stuff", s);
+    }
+
+    #[test]
+    fn invalid_escape_sequence_is_literal() {
+        let i = b"\"text\\xstuff\"";
+        let (s, _) = param_value(i).expect("parser failed");
+        assert_eq!("text\\xstuff", s);
+    }
+
+    #[test]
+    fn param_value_is_content() {
+        let i = b"\"this is a value\"";
+        let (s, _) = param_value(i).expect("parser failed");
+        assert_eq!("this is a value", s);
+    }
+
+    #[test]
+    fn param_name_must_not_be_empty() {
+        let i = b"=nothing";
+        sd_name(i).expect_err("should fail");
+    }
+
+    #[test]
+    fn param_name_is_parsed() {
+        let i = b"test=nothing";
+        let (n, _) = sd_name(i).expect("parser failed");
+        assert_eq!("test", n);
+    }
+
+    #[test]
+    fn param_name_excludes_bracket() {
+        let i = b"test]nothing";
+        let (n, _) = sd_name(i).expect("parser failed");
+        assert_eq!("test", n);
+    }
+
+    #[test]
+    fn param_is_name_value_pair() {
+        let i = b"eventSource=\"Application\"";
+        let ((name, value), _) = param(i).expect("parser failed");
+        assert_eq!("eventSource", name);
+        assert_eq!("Application", value);
+    }
+
+    #[test]
+    fn param_requires_eq_separator() {
+        let i = b"eventSource]\"Application\"";
+        param(i).expect_err("should fail");
+    }
+
+    #[test]
+    fn structured_data_elements_are_parsed() {
+        let i = b"[test name=\"value\" another=\"another value\"]";
+        let (sd, _) = structured_data_element(i).expect("parser failed");
+        assert_eq!("test", sd.id);
+        assert_eq!(2, sd.params.len());
+    }
+}
@@ -0,0 +1,645 @@
+use std::borrow::Cow;
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use serde_json::{
+    json,
+    Value,
+};
+
+use crate::data::{
+    clef,
+    insert_property,
+    rules::Rules,
+    parsers::{
+        access,
+        auditd,
+        cee,
+        cef,
+        cisco,
+        kv,
+        leef,
+    },
+    selector::Selectors,
+    syslog,
+    Config,
+};
+
+/**
+Recognize well-known vendor and application formats in the message of a CLEF event.
+
+User-defined parsing rules are tried first, followed by the profiles. The first enabled profile that matches the message is used to extract properties from it.
+Messages that don't match any profile may have `key=value` pairs extracted from them,
+if they're from a configured source. Otherwise they're left as they are.
+
+Timestamps in messages without a year are placed in the year nearest the time the message was received.
+*/
+pub(super) fn apply<'a>(clef: &mut clef::Message<'a>, config: &Config, rules: &Rules, received: &DateTime<Utc>) {
+    let message = match clef.message.take() {
+        Some(message) => message,
+        None => return,
+    };
+
+    if let Some((rule, captures)) = rule_captures(clef, rules, &message) {
+        insert_property(clef, Cow::Borrowed("parse_rule"), json!(rule));
+        for (k, v) in captures {
+            insert_property(clef, Cow::Owned(k), v);
+        }
+
+        clef.message = Some(message);
+        return;
+    }
+
+    if config.profiles.cee {
+        if let Some(cee) = cee::message(&message) {
+            return cee_into_clef(cee, clef);
+        }
+    }
+
+    if config.profiles.cisco {
+        if let Some(cisco) = cisco::message(&message, received) {
+            return cisco_into_clef(cisco, clef);
+        }
+    }
+
+    if config.profiles.cef {
+        if let Some(cef) = cef::message(&message) {
+            return cef_into_clef(cef, clef);
+        }
+    }
+
+    if config.profiles.leef {
+        if let Some(leef) = leef::message(&message) {
+            leef_into_clef(leef, clef);
+            clef.message = Some(message);
+            return;
+        }
+    }
+
+    if config.profiles.auditd {
+        if let Some(auditd) = auditd::message(&message) {
+            auditd_into_clef(auditd, clef);
+            clef.message = Some(message);
+            return;
+        }
+    }
+
+    if config.profiles.haproxy {
+        if let Some(access) = access::haproxy(&message) {
+            access_into_clef(access, clef);
+            clef.message = Some(message);
+            return;
+        }
+    }
+
+    if config.profiles.clf {
+        if let Some(access) = access::clf(&message) {
+            access_into_clef(access, clef);
+            clef.message = Some(message);
+            return;
+        }
+    }
+
+    if !config.key_value.is_empty() && matches_source(clef, &config.key_value) {
+        for (k, v) in kv::pairs(&message) {
+            insert_property(clef, Cow::Owned(k.to_owned()), json!(v));
+        }
+    }
+
+    clef.message = Some(message);
+}
+
+fn rule_captures<'r>(clef: &clef::Message, rules: &'r Rules, message: &str) -> Option<(&'r str, Vec<(String, Value)>)> {
+    let app_name = clef.additional.get("app_name").and_then(Value::as_str);
+    let hostname = clef.additional.get("hostname").and_then(Value::as_str);
+
+    rules.captures(message, app_name, hostname)
+}
+
+// Whether the app name and hostname of the event match any of the selectors
+fn matches_source(clef: &clef::Message, selectors: &Selectors) -> bool {
+    let app_name = clef.additional.get("app_name").and_then(Value::as_str);
+    let hostname = clef.additional.get("hostname").and_then(Value::as_str);
+
+    selectors.matches(app_name, hostname)
+}
+
+fn cee_into_clef(cee: cee::Message, clef: &mut clef::Message) {
+    if let Some(severity) = cee.severity {
+        clef.level = Some(Cow::Borrowed(syslog::Priority { facility: 0, severity }.severity()));
+    }
+    if let Some(time) = cee.time {
+        clef.timestamp = time.into();
+    }
+
+    for (k, v) in cee.fields {
+        insert_property(clef, Cow::Owned(k), v);
+    }
+
+    clef.message = cee.msg.map(Cow::Owned);
+}
+
+fn cisco_into_clef(cisco: cisco::Message, clef: &mut clef::Message) {
+    if let Some(sequence_number) = cisco.sequence_number {
+        insert_property(clef, Cow::Borrowed("sequence_number"), json!(sequence_number));
+    }
+    if let Some(clock) = cisco.clock {
+        insert_property(clef, Cow::Borrowed("clock"), json!(clock.name()));
+    }
+    if let Some(timestamp) = cisco.timestamp {
+        clef.timestamp = timestamp.into();
+    }
+    if let Some(unresolved_timestamp) = cisco.unresolved_timestamp {
+        insert_property(clef, Cow::Borrowed("cisco_timestamp"), json!(unresolved_timestamp));
+    }
+    if let Some(hostname) = cisco.hostname {
+        clef.additional.entry(Cow::Borrowed("hostname")).or_insert_with(|| json!(hostname));
+    }
+
+    insert_property(clef, Cow::Borrowed("cisco_facility"), json!(cisco.facility));
+    insert_property(clef, Cow::Borrowed("cisco_mnemonic"), json!(cisco.mnemonic));
+
+    // The Cisco severity is more specific than the one in the SYSLOG header
+    clef.level = Some(Cow::Borrowed(syslog::Priority { facility: 0, severity: cisco.severity }.severity()));
+
+    clef.message = if !cisco.message.is_empty() {
+        Some(Cow::Owned(cisco.message.to_owned()))
+    } else {
+        None
+    };
+}
+
+fn cef_into_clef(cef: cef::Message, clef: &mut clef::Message) {
+    if let Some(severity) = cef.syslog_severity() {
+        clef.level = Some(Cow::Borrowed(syslog::Priority { facility: 0, severity }.severity()));
+    }
+
+    let cef::Message {
+        version,
+        device_vendor,
+        device_product,
+        device_version,
+        signature_id,
+        name,
+        severity,
+        extension,
+    } = cef;
+
+    insert_property(clef, Cow::Borrowed("cef_version"), json!(version));
+    insert_property(clef, Cow::Borrowed("device_vendor"), json!(device_vendor));
+    insert_property(clef, Cow::Borrowed("device_product"), json!(device_product));
+    insert_property(clef, Cow::Borrowed("device_version"), json!(device_version));
+    insert_property(clef, Cow::Borrowed("signature_id"), json!(signature_id));
+    insert_property(clef, Cow::Borrowed("cef_severity"), json!(severity));
+
+    for (k, v) in extension {
+        insert_property(clef, Cow::Owned(k), json!(v));
+    }
+
+    clef.message = if !name.is_empty() { Some(Cow::Owned(name)) } else { None };
+}
+
+fn leef_into_clef(leef: leef::Message, clef: &mut clef::Message) {
+    if let Some(severity) = leef.syslog_severity() {
+        clef.level = Some(Cow::Borrowed(syslog::Priority { facility: 0, severity }.severity()));
+    }
+
+    insert_property(clef, Cow::Borrowed("leef_version"), json!(leef.version));
+    insert_property(clef, Cow::Borrowed("device_vendor"), json!(leef.vendor));
+    insert_property(clef, Cow::Borrowed("device_product"), json!(leef.product));
+    insert_property(clef, Cow::Borrowed("device_version"), json!(leef.product_version));
+    insert_property(clef, Cow::Borrowed("event_id"), json!(leef.event_id));
+
+    for (k, v) in leef.attributes {
+        insert_property(clef, Cow::Owned(k.to_owned()), json!(v));
+    }
+}
+
+fn auditd_into_clef(auditd: auditd::Message, clef: &mut clef::Message) {
+    // The audit timestamp is shared by all records of the same event
+    clef.timestamp = auditd.timestamp.into();
+
+    insert_property(clef, Cow::Borrowed("audit_type"), json!(auditd.record_type));
+    insert_property(clef, Cow::Borrowed("audit_serial"), json!(auditd.serial));
+    if let Some(node) = auditd.node {
+        insert_property(clef, Cow::Borrowed("audit_node"), json!(node));
+    }
+
+    for (k, v) in auditd.fields {
+        insert_property(clef, Cow::Owned(k.to_owned()), json!(v));
+    }
+}
+
+fn access_into_clef(access: access::Message, clef: &mut clef::Message) {
+    // The response status is a better indicator of the outcome than the SYSLOG severity
+    clef.level = Some(Cow::Borrowed(syslog::Priority { facility: 0, severity: access.syslog_severity() }.severity()));
+
+    insert_property(clef, Cow::Borrowed("client_ip"), json!(access.client_ip));
+    if let Some(client_port) = access.client_port {
+        insert_property(clef, Cow::Borrowed("client_port"), json!(client_port));
+    }
+    if let Some(user) = access.user {
+        insert_property(clef, Cow::Borrowed("user"), json!(user));
+    }
+
+    match (access.request.method, access.request.path) {
+        (Some(method), Some(path)) => {
+            insert_property(clef, Cow::Borrowed("method"), json!(method));
+            insert_property(clef, Cow::Borrowed("path"), json!(path));
+            if let Some(protocol) = access.request.protocol {
+                insert_property(clef, Cow::Borrowed("protocol"), json!(protocol));
+            }
+        }
+        _ => insert_property(clef, Cow::Borrowed("request"), json!(access.request.line)),
+    }
+
+    insert_property(clef, Cow::Borrowed("status"), json!(access.status));
+    if let Some(bytes) = access.bytes {
+        insert_property(clef, Cow::Borrowed("bytes"), json!(bytes));
+    }
+    if let Some(referer) = access.referer {
+        insert_property(clef, Cow::Borrowed("referer"), json!(referer));
+    }
+    if let Some(user_agent) = access.user_agent {
+        insert_property(clef, Cow::Borrowed("user_agent"), json!(user_agent));
+    }
+
+    if let Some(proxy) = access.proxy {
+        insert_property(clef, Cow::Borrowed("frontend"), json!(proxy.frontend));
+        insert_property(clef, Cow::Borrowed("backend"), json!(proxy.backend));
+        insert_property(clef, Cow::Borrowed("server"), json!(proxy.server));
+        insert_property(clef, Cow::Borrowed("termination_state"), json!(proxy.termination_state));
+
+        let timers = [
+            ("time_request_ms", proxy.timers.request),
+            ("time_queue_ms", proxy.timers.queue),
+            ("time_connect_ms", proxy.timers.connect),
+            ("time_response_ms", proxy.timers.response),
+            ("time_total_ms", proxy.timers.total),
+        ];
+        for (k, timer) in &timers {
+            if let Some(timer) = timer {
+                insert_property(clef, Cow::Borrowed(k), json!(timer));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use crate::data::{
+        Data,
+        Profiles,
+    };
+
+    fn to_clef(msg: &[u8], config: &Config) -> Value {
+        let data = Data::new(config.clone()).unwrap();
+        serde_json::to_value(data.to_clef(msg, &Utc::now())).unwrap()
+    }
+
+    fn all_profiles() -> Config {
+        Config {
+            profiles: Profiles::all(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn cisco_to_clef() {
+        let actual = to_clef(b"<189>123: *Mar  1 18:46:11.123: %LINK-3-UPDOWN: Interface FastEthernet0/0, changed state to up", &all_profiles());
+
+        assert_eq!(json!("err"), actual["@l"]);
+        assert_eq!(json!("Interface FastEthernet0/0, changed state to up"), actual["@m"]);
+        assert_eq!(json!(123), actual["sequence_number"]);
+        assert_eq!(json!("unauthoritative"), actual["clock"]);
+        assert_eq!(json!("LINK"), actual["cisco_facility"]);
+        assert_eq!(json!("UPDOWN"), actual["cisco_mnemonic"]);
+        assert_eq!(json!("local7"), actual["facility"]);
+    }
+
+    #[test]
+    fn cisco_hostname_does_not_replace_syslog_hostname() {
+        let actual = to_clef(b"<189>Oct 11 22:14:15 switch1 42: router1: %SYS-5-CONFIG_I: Configured from console", &all_profiles());
+
+        assert_eq!(json!("switch1"), actual["hostname"]);
+        assert_eq!(json!("notice"), actual["@l"]);
+    }
+
+    #[test]
+    fn cisco_properties_do_not_replace_structured_data() {
+        let actual = to_clef(b"<189>1 - switch1 - - - [clock source=\"ntp\"] 123: *Mar  1 18:46:11.123: %LINK-3-UPDOWN: Interface up", &all_profiles());
+
+        assert_eq!(json!([{ "source": "ntp" }]), actual["clock"]);
+        assert_eq!(json!("unauthoritative"), actual["__clock"]);
+    }
+
+    #[test]
+    fn cisco_year_is_inferred_from_receive_time() {
+        let data = Data::new(all_profiles()).unwrap();
+        let received = Utc.ymd(2021, 1, 1).and_hms(0, 0, 5);
+
+        let actual = serde_json::to_value(data.to_clef(b"<189>42: router1: .Dec 31 23:59:59 UTC: %SYS-5-CONFIG_I: Configured from console", &received)).unwrap();
+
+        assert_eq!(json!("2020-12-31T23:59:59Z"), actual["@t"]);
+    }
+
+    #[test]
+    fn cisco_timestamp_with_unresolved_zone_does_not_replace_syslog_timestamp() {
+        let actual = to_clef(b"<189>1 2021-10-11T22:14:15Z switch1 - - - - 42: Oct 11 15:14:15 PDT: %SYS-5-CONFIG_I: Configured from console", &all_profiles());
+
+        assert_eq!(json!("2021-10-11T22:14:15Z"), actual["@t"]);
+        assert_eq!(json!("Oct 11 15:14:15 PDT"), actual["cisco_timestamp"]);
+        assert_eq!(json!("Configured from console"), actual["@m"]);
+    }
+
+    #[test]
+    fn cisco_profile_can_be_disabled() {
+        let config = Config {
+            profiles: Profiles::none(),
+            ..Default::default()
+        };
+
+        let actual = to_clef(b"<189>123: *Mar  1 18:46:11.123: %LINK-3-UPDOWN: Interface up", &config);
+
+        assert_eq!(json!("notice"), actual["@l"]);
+        assert_eq!(json!("123: *Mar  1 18:46:11.123: %LINK-3-UPDOWN: Interface up"), actual["@m"]);
+        assert_eq!(Value::Null, actual["cisco_mnemonic"]);
+    }
+
+    #[test]
+    fn non_matching_messages_fall_back() {
+        let actual = to_clef(b"<34>Oct 11 22:14:15 mymachine su: 'su root' failed for lonvick on /dev/pts/8", &Config::default());
+
+        assert_eq!(json!("su: 'su root' failed for lonvick on /dev/pts/8"), actual["@m"]);
+        assert_eq!(json!("crit"), actual["@l"]);
+    }
+
+    #[test]
+    fn profiles_from_str() {
+        assert_eq!(Profiles::none(), Profiles::default());
+        assert_eq!(Profiles::all(), "all".parse().unwrap());
+        assert_eq!(Profiles::none(), "none".parse().unwrap());
+        assert_eq!(Profiles { cisco: true, ..Profiles::none() }, " cisco ".parse().unwrap());
+        assert_eq!(Profiles { cisco: true, cef: true, ..Profiles::none() }, "cisco, cef".parse().unwrap());
+        assert!("cisco,unknown".parse::<Profiles>().is_err());
+    }
+
+    #[test]
+    fn cef_to_clef() {
+        let actual = to_clef(b"<134>Feb 14 19:04:54 fw1 CEF:0|Security|threatmanager|1.0|100|worm successfully stopped|7|src=10.0.0.1 hostname=attacker act=blocked", &all_profiles());
+
+        assert_eq!(json!({
+            "@t": actual["@t"],
+            "@l": "err",
+            "@m": "worm successfully stopped",
+            "facility": "local0",
+            "facility_code": 16,
+            "severity_code": 6,
+            "hostname": "fw1",
+            "cef_version": "0",
+            "device_vendor": "Security",
+            "device_product": "threatmanager",
+            "device_version": "1.0",
+            "signature_id": "100",
+            "cef_severity": "7",
+            "src": "10.0.0.1",
+            "__hostname": "attacker",
+            "act": "blocked",
+        }), actual);
+    }
+
+    #[test]
+    fn cef_in_rfc5424_to_clef() {
+        let actual = to_clef(b"<134>1 2003-10-11T22:14:15.003Z fw1 firewall - - - CEF:0|Vendor|Product|1.0|100|Denied|Unknown|src=10.0.0.1", &all_profiles());
+
+        assert_eq!(json!("info"), actual["@l"]);
+        assert_eq!(json!("Denied"), actual["@m"]);
+        assert_eq!(json!("10.0.0.1"), actual["src"]);
+    }
+
+    #[test]
+    fn leef_to_clef() {
+        let actual = to_clef(b"<134>Feb 14 19:04:54 qradar1 LEEF:2.0|Lancope|StealthWatch|1.0|41|^|src=192.0.2.0^dst=172.50.123.1^sev=8", &all_profiles());
+
+        assert_eq!(json!({
+            "@t": actual["@t"],
+            "@l": "err",
+            "@m": "LEEF:2.0|Lancope|StealthWatch|1.0|41|^|src=192.0.2.0^dst=172.50.123.1^sev=8",
+            "facility": "local0",
+            "facility_code": 16,
+            "severity_code": 6,
+            "hostname": "qradar1",
+            "leef_version": "2.0",
+            "device_vendor": "Lancope",
+            "device_product": "StealthWatch",
+            "device_version": "1.0",
+            "event_id": "41",
+            "src": "192.0.2.0",
+            "dst": "172.50.123.1",
+            "sev": "8",
+        }), actual);
+    }
+
+    #[test]
+    fn leef_in_rfc5424_to_clef() {
+        let actual = to_clef(b"<134>1 2003-10-11T22:14:15.003Z host1 exchange - - - LEEF:1.0|Microsoft|MSExchange|4.0 SP1|15345|src=192.0.2.0\tsev=2", &all_profiles());
+
+        assert_eq!(json!("info"), actual["@l"]);
+        assert_eq!(json!("LEEF:1.0|Microsoft|MSExchange|4.0 SP1|15345|src=192.0.2.0\tsev=2"), actual["@m"]);
+        assert_eq!(json!("192.0.2.0"), actual["src"]);
+        assert_eq!(json!("exchange"), actual["app_name"]);
+    }
+
+    #[test]
+    fn key_value_pairs_to_clef() {
+        let config = Config {
+            key_value: "app_name=fortigate".parse().unwrap(),
+            ..Default::default()
+        };
+
+        let input = b"<134>1 2019-05-10T11:37:47Z fw1 fortigate - - - date=2019-05-10 logid=\"0000000013\" type=\"traffic\" hostname=other";
+        let actual = to_clef(input, &config);
+
+        assert_eq!(json!("date=2019-05-10 logid=\"0000000013\" type=\"traffic\" hostname=other"), actual["@m"]);
+        assert_eq!(json!("2019-05-10"), actual["date"]);
+        assert_eq!(json!("0000000013"), actual["logid"]);
+        assert_eq!(json!("traffic"), actual["type"]);
+        assert_eq!(json!("fw1"), actual["hostname"]);
+        assert_eq!(json!("other"), actual["__hostname"]);
+    }
+
+    #[test]
+    fn key_value_pairs_from_rfc3164_tags() {
+        let config = Config {
+            key_value: "app_name=fortigate".parse().unwrap(),
+            parse_rfc3164_tags: true,
+            ..Default::default()
+        };
+
+        let actual = to_clef(b"<134>May 10 11:37:47 fw1 fortigate[42]: logid=\"0000000013\" type=\"traffic\"", &config);
+
+        assert_eq!(json!("fortigate"), actual["app_name"]);
+        assert_eq!(json!("42"), actual["proc_id"]);
+        assert_eq!(json!("0000000013"), actual["logid"]);
+        assert_eq!(json!("traffic"), actual["type"]);
+    }
+
+    #[test]
+    fn key_value_pairs_only_from_configured_sources() {
+        let config = Config {
+            key_value: "app_name=fortigate".parse().unwrap(),
+            ..Default::default()
+        };
+
+        let actual = to_clef(b"<134>1 2019-05-10T11:37:47Z web1 nginx - - - status=200", &config);
+        assert_eq!(Value::Null, actual["status"]);
+
+        let actual = to_clef(b"<134>1 2019-05-10T11:37:47Z web1 nginx - - - status=200", &Config::default());
+        assert_eq!(Value::Null, actual["status"]);
+    }
+
+    #[test]
+    fn cee_to_clef() {
+        let actual = to_clef(b"<134>Oct 11 22:14:15 web1 sshd[42]: @cee: {\"msg\": \"Accepted publickey\", \"pri\": 36, \"time\": \"2023-10-11T22:14:15.003Z\", \"user\": {\"name\": \"root\"}, \"hostname\": \"other\"}", &all_profiles());
+
+        assert_eq!(json!({
+            "@t": "2023-10-11T22:14:15.003Z",
+            "@l": "warning",
+            "@m": "Accepted publickey",
+            "facility": "local0",
+            "facility_code": 16,
+            "severity_code": 6,
+            "hostname": "web1",
+            "user": { "name": "root" },
+            "__hostname": "other",
+        }), actual);
+    }
+
+    #[test]
+    fn reserved_names_are_escaped() {
+        let actual = to_clef(b"<134>1 2003-10-11T22:14:15.003Z host1 app - - - @cee:{\"msg\": \"Accepted\", \"@m\": \"other\", \"@l\": \"debug\"}", &all_profiles());
+
+        assert_eq!(json!("Accepted"), actual["@m"]);
+        assert_eq!(json!("info"), actual["@l"]);
+        assert_eq!(json!("other"), actual["@@m"]);
+        assert_eq!(json!("debug"), actual["@@l"]);
+
+        let config = Config {
+            key_value: "*".parse().unwrap(),
+            ..Default::default()
+        };
+
+        let actual = to_clef(b"<134>1 2003-10-11T22:14:15.003Z host1 app - - - @t=yesterday status=200", &config);
+
+        assert_eq!(json!("2003-10-11T22:14:15.003Z"), actual["@t"]);
+        assert_eq!(json!("yesterday"), actual["@@t"]);
+        assert_eq!(json!("200"), actual["status"]);
+    }
+
+    #[test]
+    fn cee_without_msg_has_no_message() {
+        let actual = to_clef(b"<134>1 2003-10-11T22:14:15.003Z host1 app - - - @cee:{\"event\":\"login\"}", &all_profiles());
+
+        assert_eq!(Value::Null, actual["@m"]);
+        assert_eq!(json!("info"), actual["@l"]);
+        assert_eq!(json!("login"), actual["event"]);
+    }
+
+    #[test]
+    fn auditd_to_clef() {
+        let actual = to_clef(b"<85>Nov 14 22:13:20 host1 audispd: node=host1 type=EXECVE msg=audit(1700000000.123:4567): argc=2 a0=\"ls\" a1=\"-la\" proctitle=6C73002D6C61", &all_profiles());
+
+        assert_eq!(json!({
+            "@t": "2023-11-14T22:13:20.123Z",
+            "@l": "notice",
+            "@m": "audispd: node=host1 type=EXECVE msg=audit(1700000000.123:4567): argc=2 a0=\"ls\" a1=\"-la\" proctitle=6C73002D6C61",
+            "facility": "authpriv",
+            "facility_code": 10,
+            "severity_code": 5,
+            "hostname": "host1",
+            "audit_type": "EXECVE",
+            "audit_serial": 4567,
+            "audit_node": "host1",
+            "argc": "2",
+            "a0": "ls",
+            "a1": "-la",
+            "proctitle": "ls -la",
+        }), actual);
+    }
+
+    #[test]
+    fn clf_to_clef() {
+        let input = b"<190>Oct 10 13:55:36 web1 nginx: 192.0.2.1 - frank [10/Oct/2000:13:55:36 -0700] \"GET /index.html HTTP/1.1\" 502 157 \"-\" \"curl/7.68.0\"";
+        let actual = to_clef(input, &all_profiles());
+
+        assert_eq!(json!({
+            "@t": actual["@t"],
+            "@l": "err",
+            "@m": "nginx: 192.0.2.1 - frank [10/Oct/2000:13:55:36 -0700] \"GET /index.html HTTP/1.1\" 502 157 \"-\" \"curl/7.68.0\"",
+            "facility": "local7",
+            "facility_code": 23,
+            "severity_code": 6,
+            "hostname": "web1",
+            "client_ip": "192.0.2.1",
+            "user": "frank",
+            "method": "GET",
+            "path": "/index.html",
+            "protocol": "HTTP/1.1",
+            "status": 502,
+            "bytes": 157,
+            "user_agent": "curl/7.68.0",
+        }), actual);
+    }
+
+    #[test]
+    fn haproxy_to_clef() {
+        let input = b"<134>1 2009-02-06T12:14:14.655Z lb1 haproxy 14389 - - 192.0.2.1:33317 [06/Feb/2009:12:14:14.655] http-in static/srv1 10/0/30/-1/109 404 2750 - - ---- 1/1/1/1/0 0/0 \"GET /missing HTTP/1.1\"";
+        let actual = to_clef(input, &all_profiles());
+
+        assert_eq!(json!("warning"), actual["@l"]);
+        assert_eq!(json!("192.0.2.1"), actual["client_ip"]);
+        assert_eq!(json!(33317), actual["client_port"]);
+        assert_eq!(json!("static"), actual["backend"]);
+        assert_eq!(json!("srv1"), actual["server"]);
+        assert_eq!(json!(404), actual["status"]);
+        assert_eq!(json!(30), actual["time_connect_ms"]);
+        assert_eq!(Value::Null, actual["time_response_ms"]);
+        assert_eq!(json!(109), actual["time_total_ms"]);
+    }
+
+    #[test]
+    fn parse_rules_to_clef() {
+        let config = Config {
+            parse_rules: "sonicwall [app_name=sonicwall] ^id=%{WORD:firewall} src=%{IP:src}:%{POSINT:src_port:int} app_name=%{WORD:app_name}".parse().unwrap(),
+            ..Default::default()
+        };
+
+        let actual = to_clef(b"<134>1 2019-05-10T11:37:47Z fw1 sonicwall - - - id=firewall src=192.0.2.1:443 app_name=other", &config);
+
+        assert_eq!(json!("id=firewall src=192.0.2.1:443 app_name=other"), actual["@m"]);
+        assert_eq!(json!("sonicwall"), actual["parse_rule"]);
+        assert_eq!(json!("firewall"), actual["firewall"]);
+        assert_eq!(json!("192.0.2.1"), actual["src"]);
+        assert_eq!(json!(443), actual["src_port"]);
+        assert_eq!(json!("sonicwall"), actual["app_name"]);
+        assert_eq!(json!("other"), actual["__app_name"]);
+    }
+
+    #[test]
+    fn parse_rules_take_precedence_over_profiles() {
+        let config = Config {
+            parse_rules: r"custom ^CEF:(?P<cef>\d)".parse().unwrap(),
+            ..all_profiles()
+        };
+
+        let actual = to_clef(b"<134>1 2003-10-11T22:14:15.003Z fw1 firewall - - - CEF:0|Vendor|Product|1.0|100|Denied|Unknown|src=10.0.0.1", &config);
+
+        assert_eq!(json!("custom"), actual["parse_rule"]);
+        assert_eq!(json!("0"), actual["cef"]);
+        assert_eq!(Value::Null, actual["device_vendor"]);
+    }
+}
@@ -0,0 +1,264 @@
+// Reassembly of events that senders split across several messages, like stack traces
+// delivered one line per datagram.
+//
+// Messages are grouped by their hostname, app name, and process id. A message that matches
+// one of the start-of-event patterns begins a new event, and any others are continuations
+// of the event before them. Events are held until their next event starts, or until no
+// continuation has arrived for the flush timeout.
+
+use std::{
+    collections::HashMap,
+    mem,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+
+use crate::{
+    data::{parsers, syslog},
+    error::{err_msg, Error},
+};
+
+// Events with this many continuations are flushed without waiting for more
+const MAX_CONTINUATIONS: usize = 1000;
+
+/**
+Configuration for reassembling events split across several messages.
+*/
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Config {
+    /**
+    Patterns that match the message text of the first message in an event.
+
+    Reassembly is disabled when there are no patterns.
+    */
+    pub start_patterns: Vec<String>,
+    /**
+    How long to wait for a continuation before an event is flushed.
+    */
+    pub flush_timeout: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            start_patterns: vec![],
+            flush_timeout: Duration::from_millis(1000),
+        }
+    }
+}
+
+impl Config {
+    pub(super) fn is_enabled(&self) -> bool {
+        !self.start_patterns.is_empty()
+    }
+}
+
+/**
+An event made up of its first message, along with the text of any continuations.
+*/
+#[derive(Debug)]
+pub(super) struct Event<T> {
+    pub first: T,
+    pub continuations: Vec<String>,
+    pub received: DateTime<Utc>,
+    last_received: Instant,
+}
+
+type Key = (Option<String>, Option<String>, Option<String>);
+
+pub(super) struct Reassembler<T> {
+    start_patterns: Vec<Regex>,
+    flush_timeout: Duration,
+    pending: Mutex<HashMap<Key, Event<T>>>,
+}
+
+impl<T> Reassembler<T> {
+    pub(super) fn new(config: &Config) -> Result<Self, Error> {
+        let start_patterns = config
+            .start_patterns
+            .iter()
+            .map(|pattern| Regex::new(pattern).map_err(|err| err_msg(format!("invalid start-of-event pattern `{}`: {}", pattern, err))))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Reassembler {
+            start_patterns,
+            flush_timeout: config.flush_timeout,
+            pending: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /**
+    The interval to check for events to flush on.
+    */
+    pub(super) fn flush_interval(&self) -> Duration {
+        (self.flush_timeout / 4).max(Duration::from_millis(10))
+    }
+
+    /**
+    Add a message, returning any events that are complete because of it.
+
+    The message should be parsed keeping the indentation of its text, which is significant
+    in continuations. If it starts a new event then it's converted into the event's first
+    message with `first`.
+    */
+    pub(super) fn push<'a>(
+        &self,
+        syslog: syslog::Message<'a>,
+        received: DateTime<Utc>,
+        now: Instant,
+        first: impl FnOnce(syslog::Message<'a>) -> T,
+    ) -> Vec<Event<T>> {
+        let text = syslog.message.as_deref().unwrap_or("");
+
+        // RFC3164 messages carry their app name and process id in a tag before the text, like `app[42]: `.
+        // The tag identifies the source even when it isn't parsed into the app name.
+        let (tag, text) = match parsers::tag(text.as_bytes()) {
+            Ok(((app_name, proc_id), rem)) if syslog.app_name.map(|name| name == app_name).unwrap_or(true) => {
+                (Some((app_name, proc_id)), &text[text.len() - rem.len()..])
+            }
+            _ => (None, text),
+        };
+
+        let key = (
+            syslog.hostname.map(str::to_owned),
+            syslog.app_name.or_else(|| tag.map(|(app_name, _)| app_name)).map(str::to_owned),
+            syslog.proc_id.or_else(|| tag.and_then(|(_, proc_id)| proc_id)).map(str::to_owned),
+        );
+        let is_start = self.start_patterns.iter().any(|pattern| pattern.is_match(text));
+
+        let mut pending = self.pending.lock().expect("reassembly lock poisoned");
+        let mut complete = vec![];
+
+        match pending.get_mut(&key) {
+            Some(event) if !is_start => {
+                increment!(data.msg_reassembled);
+
+                event.continuations.push(text.to_owned());
+                event.last_received = now;
+
+                if event.continuations.len() >= MAX_CONTINUATIONS {
+                    complete.extend(pending.remove(&key));
+                }
+            }
+            _ => {
+                let event = Event {
+                    first: first(syslog),
+                    continuations: vec![],
+                    received,
+                    last_received: now,
+                };
+
+                complete.extend(pending.insert(key, event));
+            }
+        }
+
+        complete
+    }
+
+    /**
+    Take any events that haven't received a continuation within the flush timeout.
+    */
+    pub(super) fn flush_expired(&self, now: Instant) -> Vec<Event<T>> {
+        let mut pending = self.pending.lock().expect("reassembly lock poisoned");
+
+        let expired: Vec<Key> = pending
+            .iter()
+            .filter(|(_, event)| now.saturating_duration_since(event.last_received) >= self.flush_timeout)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        expired.into_iter().filter_map(|key| pending.remove(&key)).collect()
+    }
+
+    /**
+    Take all pending events.
+    */
+    pub(super) fn flush_all(&self) -> Vec<Event<T>> {
+        let mut pending = self.pending.lock().expect("reassembly lock poisoned");
+
+        mem::take(&mut *pending).into_iter().map(|(_, event)| event).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+    use crate::data::charset::Charsets;
+
+    fn reassembler(start_patterns: &[&str]) -> Reassembler<String> {
+        let config = Config {
+            start_patterns: start_patterns.iter().map(|pattern| pattern.to_string()).collect(),
+            flush_timeout: Duration::from_millis(100),
+        };
+
+        Reassembler::new(&config).unwrap()
+    }
+
+    fn push(reassembler: &Reassembler<String>, msg: &[u8], now: Instant) -> Vec<Event<String>> {
+        let (syslog, _) = syslog::Message::from_bytes_with_charsets(msg, syslog::ParseMode::Auto, &Charsets::default(), true, false, &Utc::now());
+
+        reassembler.push(syslog, Utc::now(), now, |syslog| syslog.message.map(Cow::into_owned).unwrap_or_default())
+    }
+
+    #[test]
+    fn continuations_are_added_to_their_event() {
+        let reassembler = reassembler(&[r"^\S"]);
+        let now = Instant::now();
+
+        assert!(push(&reassembler, b"<27>1 2003-10-11T22:14:15.003Z host1 app 42 - - Request failed", now).is_empty());
+        assert!(push(&reassembler, b"<27>1 2003-10-11T22:14:15.004Z host1 app 42 - - \tat com.example.Main.main(Main.java:7)", now).is_empty());
+        assert!(push(&reassembler, b"<27>1 2003-10-11T22:14:15.004Z host1 other 42 - - Unrelated", now).is_empty());
+
+        let complete = push(&reassembler, b"<27>1 2003-10-11T22:14:16.003Z host1 app 42 - - Next request", now);
+
+        assert_eq!(1, complete.len());
+        assert_eq!("Request failed", complete[0].first);
+        assert_eq!(vec!["\tat com.example.Main.main(Main.java:7)".to_owned()], complete[0].continuations);
+
+        assert_eq!(2, reassembler.flush_all().len());
+    }
+
+    #[test]
+    fn rfc3164_tags_are_part_of_the_key() {
+        let reassembler = reassembler(&[r"^\S"]);
+        let now = Instant::now();
+
+        push(&reassembler, b"<27>Oct 11 22:14:15 host1 app[42]: Request failed", now);
+        push(&reassembler, b"<27>Oct 11 22:14:15 host1 other[7]: \tat Unrelated.main(Main.java:1)", now);
+        push(&reassembler, b"<27>Oct 11 22:14:15 host1 app[42]: \tat com.example.Main.main(Main.java:7)", now);
+
+        let mut complete = reassembler.flush_all();
+        complete.sort_by_key(|event| event.first.clone());
+
+        assert_eq!(vec!["\tat com.example.Main.main(Main.java:7)".to_owned()], complete[0].continuations);
+        assert!(complete[1].continuations.is_empty());
+    }
+
+    #[test]
+    fn events_are_flushed_after_the_timeout() {
+        let reassembler = reassembler(&[r"^\S"]);
+        let now = Instant::now();
+
+        push(&reassembler, b"<27>1 2003-10-11T22:14:15.003Z host1 app 42 - - Request failed", now);
+        push(&reassembler, b"<27>1 2003-10-11T22:14:15.003Z host2 app 42 - - Request failed", now + Duration::from_millis(60));
+
+        assert!(reassembler.flush_expired(now + Duration::from_millis(50)).is_empty());
+        assert_eq!(1, reassembler.flush_expired(now + Duration::from_millis(100)).len());
+        assert_eq!(1, reassembler.flush_expired(now + Duration::from_millis(200)).len());
+        assert!(reassembler.flush_all().is_empty());
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        let config = Config {
+            start_patterns: vec!["(unclosed".to_owned()],
+            ..Default::default()
+        };
+
+        assert!(Reassembler::<String>::new(&config).is_err());
+    }
+}
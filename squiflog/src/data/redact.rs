@@ -0,0 +1,553 @@
+// Sensitive data redacted from events before they leave the collector, like card numbers,
+// credentials, and passwords typed into username prompts:
+//
+//   mask pan
+//   hash invalid-user
+//   drop secret
+//   mask customer-id CUST-\d{6}
+//
+// Each rule has an action, a name, and a regular expression. Rules without a regular expression
+// use the built-in detector with the same name. When a regular expression has a group named
+// `secret` then only that group is redacted, otherwise the whole match is.
+//
+// Rules apply to the message, template, exception, and every string property, including
+// structured data params. Matches are masked with `***`, hashed with HMAC-SHA256 so the same
+// value can still be correlated across events, or the field containing them is dropped.
+
+use std::{borrow::Cow, fmt, ops::Range, str::FromStr};
+
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use serde_json::Value;
+use sha2::Sha256;
+
+use crate::{
+    data::{clef, Config},
+    error::{err_msg, Error},
+};
+
+const MASK: &str = "***";
+
+// The number of hex digits of a hashed value to include
+const HASH_DIGITS: usize = 16;
+
+/**
+Rules for redacting sensitive data from events.
+
+Rules are written one per line as `mask`, `hash`, or `drop`, a name, and an optional regular
+expression, like `mask customer-id CUST-\d{6}`. Rules without a regular expression use the
+built-in detector with the same name. Lines starting with `#` are ignored.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct RedactionRules(Vec<RedactionRule>);
+
+#[derive(Debug, Clone)]
+struct RedactionRule {
+    action: Action,
+    detector: Detector,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Action {
+    Mask,
+    Hash,
+    Drop,
+}
+
+#[derive(Clone)]
+struct Detector {
+    pattern: Regex,
+    // Properties with these names are redacted entirely
+    names: Option<Regex>,
+    // The secrets within a match, like the card numbers in a run of digits
+    secrets: fn(&str) -> Vec<Range<usize>>,
+}
+
+impl fmt::Debug for Detector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Detector").field("pattern", &self.pattern).field("names", &self.names).finish()
+    }
+}
+
+/**
+The key to hash redacted values with.
+
+The key is never included in diagnostics.
+*/
+#[derive(Clone, Default)]
+pub struct Key(Option<Vec<u8>>);
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(if self.0.is_some() { "Key(..)" } else { "Key(None)" })
+    }
+}
+
+impl FromStr for Key {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Key(Some(s.as_bytes().to_owned()).filter(|key| !key.is_empty())))
+    }
+}
+
+impl RedactionRules {
+    /**
+    Check that the rules can be applied with the given key.
+    */
+    pub(super) fn check(&self, key: &Key) -> Result<(), Error> {
+        if key.0.is_none() && self.0.iter().any(|rule| rule.action == Action::Hash) {
+            return Err(err_msg("redaction rules that `hash` values need a redaction key"));
+        }
+
+        Ok(())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn redact(&self, name: Option<&str>, text: &str, key: &Key) -> Redacted {
+        let mut redacted = Cow::Borrowed(text);
+
+        for rule in &self.0 {
+            let is_named = match (&rule.detector.names, name) {
+                (Some(names), Some(name)) => names.is_match(name),
+                _ => false,
+            };
+
+            if is_named {
+                if rule.action == Action::Drop {
+                    return Redacted::Dropped;
+                }
+
+                redacted = Cow::Owned(rule.replacement(&redacted, key));
+                continue;
+            }
+
+            match rule.replace_all(&redacted, key) {
+                Some(_) if rule.action == Action::Drop => return Redacted::Dropped,
+                Some(replaced) => redacted = Cow::Owned(replaced),
+                None => (),
+            }
+        }
+
+        match redacted {
+            Cow::Borrowed(_) => Redacted::Unchanged,
+            Cow::Owned(redacted) => Redacted::Replaced(redacted),
+        }
+    }
+}
+
+enum Redacted {
+    Unchanged,
+    Replaced(String),
+    Dropped,
+}
+
+impl RedactionRule {
+    // Replace the secrets in some text, returning `None` if there aren't any
+    fn replace_all(&self, text: &str, key: &Key) -> Option<String> {
+        let mut replaced = String::new();
+        let mut unreplaced_from = 0;
+
+        for captures in self.detector.pattern.captures_iter(text) {
+            let matched = match captures.name("secret").or_else(|| captures.get(0)) {
+                Some(matched) => matched,
+                None => continue,
+            };
+
+            for secret in (self.detector.secrets)(matched.as_str()) {
+                let (start, end) = (matched.start() + secret.start, matched.start() + secret.end);
+                if start == end {
+                    continue;
+                }
+
+                replaced.push_str(&text[unreplaced_from..start]);
+                replaced.push_str(&self.replacement(&text[start..end], key));
+                unreplaced_from = end;
+            }
+        }
+
+        if unreplaced_from == 0 {
+            return None;
+        }
+
+        replaced.push_str(&text[unreplaced_from..]);
+        Some(replaced)
+    }
+
+    fn replacement(&self, secret: &str, key: &Key) -> String {
+        match (self.action, &key.0) {
+            (Action::Hash, Some(key)) => hash(secret, key),
+            _ => MASK.to_owned(),
+        }
+    }
+}
+
+fn hash(secret: &str, key: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(secret.as_bytes());
+
+    let digest = mac.finalize().into_bytes();
+
+    let mut hashed = String::from("hmac:");
+    for b in digest.iter().take(HASH_DIGITS / 2) {
+        hashed.push_str(&format!("{:02x}", b));
+    }
+
+    hashed
+}
+
+impl FromStr for RedactionRules {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let mut parts = line.splitn(3, char::is_whitespace);
+                let invalid = || {
+                    err_msg(format!(
+                        "invalid redaction rule `{}`, expected `mask`, `hash`, or `drop`, a name, and an optional pattern",
+                        line
+                    ))
+                };
+
+                let action = match parts.next() {
+                    Some("mask") => Action::Mask,
+                    Some("hash") => Action::Hash,
+                    Some("drop") => Action::Drop,
+                    _ => return Err(invalid()),
+                };
+                let name = parts.next().filter(|name| !name.is_empty()).ok_or_else(invalid)?;
+
+                let detector = match parts.next().map(str::trim).filter(|pattern| !pattern.is_empty()) {
+                    Some(pattern) => Detector {
+                        pattern: Regex::new(pattern).map_err(|err| err_msg(format!("invalid redaction rule `{}`: {}", name, err)))?,
+                        names: None,
+                        secrets: whole_match,
+                    },
+                    None => built_in(name).ok_or_else(|| {
+                        err_msg(format!(
+                            "unrecognized redaction detector `{}`, expected a pattern or one of `pan`, `secret`, `bearer`, `jwt`, `aws-key`, or `invalid-user`",
+                            name
+                        ))
+                    })?,
+                };
+
+                Ok(RedactionRule { action, detector })
+            })
+            .collect::<Result<_, _>>()
+            .map(RedactionRules)
+    }
+}
+
+fn built_in(name: &str) -> Option<Detector> {
+    lazy_static! {
+        static ref PAN: Regex = Regex::new(r"\b(?:\d[ -]?){12,}\d\b").unwrap();
+        static ref SECRET: Regex = Regex::new(
+            r#"(?i)\b(?:password|passwd|pwd|secret|client_secret|token|access_token|refresh_token|api_?key|access_?key)\s*[=:]\s*(?P<secret>"[^"]*"|'[^']*'|[^\s,;&]+)"#
+        )
+        .unwrap();
+        static ref SECRET_NAMES: Regex =
+            Regex::new(r"(?i)^(?:password|passwd|pwd|secret|client_secret|token|access_token|refresh_token|api_?key|access_?key|authorization)$").unwrap();
+        static ref BEARER: Regex = Regex::new(r"(?i)\bbearer\s+(?P<secret>[A-Za-z0-9\-._~+/]+=*)").unwrap();
+        static ref JWT: Regex = Regex::new(r"\beyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+").unwrap();
+        static ref AWS_KEY: Regex = Regex::new(r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b").unwrap();
+        static ref INVALID_USER: Regex = Regex::new(r"(?i)\binvalid user (?P<secret>\S+)").unwrap();
+    }
+
+    let detector = |pattern: &Regex| Detector {
+        pattern: pattern.clone(),
+        names: None,
+        secrets: whole_match,
+    };
+
+    match name {
+        "pan" => Some(Detector {
+            secrets: card_numbers,
+            ..detector(&PAN)
+        }),
+        "secret" => Some(Detector {
+            names: Some(SECRET_NAMES.clone()),
+            ..detector(&SECRET)
+        }),
+        "bearer" => Some(detector(&BEARER)),
+        "jwt" => Some(detector(&JWT)),
+        "aws-key" => Some(detector(&AWS_KEY)),
+        "invalid-user" => Some(detector(&INVALID_USER)),
+        _ => None,
+    }
+}
+
+fn whole_match(secret: &str) -> Vec<Range<usize>> {
+    std::iter::once(0..secret.len()).collect()
+}
+
+// Card numbers in a run of digits, which may be next to other digit groups like `4111 1111 1111 1111 22`
+//
+// Every window of 13 to 19 digits that starts and ends at the edge of a digit group is checked,
+// and overlapping windows that pass the Luhn check are redacted together.
+fn card_numbers(run: &str) -> Vec<Range<usize>> {
+    let digits: Vec<usize> = run.bytes().enumerate().filter(|(_, b)| b.is_ascii_digit()).map(|(i, _)| i).collect();
+    let is_group_start = |d: usize| d == 0 || digits[d] != digits[d - 1] + 1;
+
+    let mut card_numbers: Vec<Range<usize>> = Vec::new();
+    for start in (0..digits.len()).filter(|&d| is_group_start(d)) {
+        for end in (start + 13..=start + 19).filter(|&end| end <= digits.len() && (end == digits.len() || is_group_start(end))) {
+            let candidate = digits[start]..digits[end - 1] + 1;
+            if !is_luhn_valid(&run[candidate.clone()]) {
+                continue;
+            }
+
+            match card_numbers.last_mut() {
+                Some(last) if last.end >= candidate.start => last.end = last.end.max(candidate.end),
+                _ => card_numbers.push(candidate),
+            }
+        }
+    }
+
+    card_numbers
+}
+
+// Card numbers end with a Luhn check digit
+fn is_luhn_valid(pan: &str) -> bool {
+    let digits = pan.bytes().filter(u8::is_ascii_digit).map(|b| u32::from(b - b'0'));
+
+    let sum: u32 = digits
+        .rev()
+        .enumerate()
+        .map(|(i, digit)| match (i % 2, digit * 2) {
+            (1, doubled) if doubled > 9 => doubled - 9,
+            (1, doubled) => doubled,
+            _ => digit,
+        })
+        .sum();
+
+    sum % 10 == 0
+}
+
+/**
+Redact sensitive data from an event.
+
+Returns whether anything was redacted.
+*/
+pub(super) fn apply(clef: &mut clef::Message, config: &Config) -> bool {
+    let rules = &config.redaction_rules;
+    if rules.is_empty() {
+        return false;
+    }
+
+    let key = &config.redaction_key;
+    let mut is_redacted = false;
+
+    for field in [&mut clef.message, &mut clef.message_template, &mut clef.exception] {
+        let redacted = match field.as_deref() {
+            Some(text) => rules.redact(None, text, key),
+            None => continue,
+        };
+
+        match redacted {
+            Redacted::Unchanged => continue,
+            Redacted::Replaced(text) => *field = Some(Cow::Owned(text)),
+            Redacted::Dropped => *field = None,
+        }
+
+        is_redacted = true;
+    }
+
+    let mut dropped = Vec::new();
+    for (name, value) in clef.additional.iter_mut() {
+        match redact_value(rules, Some(name), value, key) {
+            Redacted::Unchanged => continue,
+            Redacted::Replaced(_) => (),
+            Redacted::Dropped => dropped.push(name.clone()),
+        }
+
+        is_redacted = true;
+    }
+
+    for name in dropped {
+        clef.additional.remove(&name);
+    }
+
+    is_redacted
+}
+
+// Redact a property value in place, recursing into structured data
+fn redact_value(rules: &RedactionRules, name: Option<&str>, value: &mut Value, key: &Key) -> Redacted {
+    let redacted = match value {
+        Value::String(text) => rules.redact(name, text, key),
+        // Numbers are redacted too, since card numbers in structured data can be inferred as numbers
+        Value::Number(number) => rules.redact(name, &number.to_string(), key),
+        Value::Array(values) => {
+            let mut is_redacted = false;
+            let mut kept = Vec::with_capacity(values.len());
+            for mut value in values.drain(..) {
+                match redact_value(rules, name, &mut value, key) {
+                    Redacted::Unchanged => {
+                        kept.push(value);
+                        continue;
+                    }
+                    Redacted::Replaced(_) => kept.push(value),
+                    Redacted::Dropped => (),
+                }
+
+                is_redacted = true;
+            }
+
+            *values = kept;
+            return changed(is_redacted);
+        }
+        Value::Object(values) => {
+            let mut is_redacted = false;
+            let mut dropped = Vec::new();
+            for (name, value) in values.iter_mut() {
+                match redact_value(rules, Some(name), value, key) {
+                    Redacted::Unchanged => continue,
+                    Redacted::Replaced(_) => (),
+                    Redacted::Dropped => dropped.push(name.clone()),
+                }
+
+                is_redacted = true;
+            }
+
+            let is_dropped = !dropped.is_empty();
+            for name in dropped {
+                values.remove(&name);
+            }
+
+            // Objects left empty, like list-formatted structured data params, are dropped with them
+            if is_dropped && values.is_empty() {
+                return Redacted::Dropped;
+            }
+
+            return changed(is_redacted);
+        }
+        _ => Redacted::Unchanged,
+    };
+
+    if let Redacted::Replaced(text) = &redacted {
+        *value = Value::String(text.clone());
+    }
+
+    redacted
+}
+
+fn changed(is_redacted: bool) -> Redacted {
+    if is_redacted {
+        Redacted::Replaced(String::new())
+    } else {
+        Redacted::Unchanged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::Utc;
+    use serde_json::json;
+
+    use crate::data::Data;
+
+    fn data(rules: &str, key: &str) -> Data {
+        Data::new(Config {
+            redaction_rules: rules.parse().unwrap(),
+            redaction_key: key.parse().unwrap(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn built_in_detectors_are_masked() {
+        let data = data("mask pan\nmask secret\nmask bearer\nmask invalid-user", "");
+
+        let cases = [
+            ("paid with 4111 1111 1111 1111 today", "paid with *** today"),
+            ("order 4111111111111112 shipped", "order 4111111111111112 shipped"),
+            ("card 4111 1111 1111 1111 22 expired", "card *** 22 expired"),
+            ("ref 12 5500-0000-0000-0004", "ref ***"),
+            ("login password=hunter2 user=alice", "login password=*** user=alice"),
+            ("Authorization: Bearer abc.def-123", "Authorization: Bearer ***"),
+            ("Failed password for invalid user hunter2 from 10.0.0.1 port 22", "Failed password for invalid user *** from 10.0.0.1 port 22"),
+        ];
+
+        for (message, expected) in &cases {
+            let msg = format!("<34>1 2003-10-11T22:14:15.003Z host1 sshd - - - {}", message);
+            let clef = data.to_clef(msg.as_bytes(), &Utc::now());
+
+            assert_eq!(Some(*expected), clef.message.as_deref(), "{}", message);
+        }
+    }
+
+    #[test]
+    fn values_are_hashed_with_key() {
+        let data = data("hash invalid-user", "s3cr3t");
+
+        let first = data.to_clef(b"<34>1 2003-10-11T22:14:15.003Z host1 sshd - - - Invalid user hunter2 from 10.0.0.1", &Utc::now());
+        let second = data.to_clef(b"<34>1 2003-10-11T22:14:16.003Z host1 sshd - - - Invalid user hunter2 from 10.0.0.2", &Utc::now());
+
+        let first = first.message.unwrap();
+        assert!(!first.contains("hunter2"), "{}", first);
+        assert!(first.starts_with("Invalid user hmac:"), "{}", first);
+        assert_eq!(&first[..34], &second.message.unwrap()[..34]);
+    }
+
+    #[test]
+    fn properties_and_structured_data_are_redacted() {
+        let data = data("drop secret\nmask card-ref CARD-(?P<secret>\\d+)", "");
+
+        let clef = data.to_clef(
+            b"<34>1 2003-10-11T22:14:15.003Z host1 app - - [auth@32473 user=\"alice\" password=\"hunter2\" ref=\"CARD-1234\"] login",
+            &Utc::now(),
+        );
+        let actual = serde_json::to_value(clef).unwrap();
+
+        assert_eq!(json!([{ "user": "alice" }, { "ref": "CARD-***" }]), actual["auth@32473"]);
+    }
+
+    #[test]
+    fn hash_rules_need_a_key() {
+        let config = Config {
+            redaction_rules: "hash pan".parse().unwrap(),
+            ..Default::default()
+        };
+
+        assert!(Data::new(config).is_err());
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        assert!("mask".parse::<RedactionRules>().is_err());
+        assert!("blur pan".parse::<RedactionRules>().is_err());
+        assert!("mask not-a-detector".parse::<RedactionRules>().is_err());
+        assert!("mask broken (".parse::<RedactionRules>().is_err());
+    }
+
+    #[test]
+    fn card_numbers_next_to_other_digits() {
+        let cases = [
+            ("4111 1111 1111 1111 22", vec![(0, 19)]),
+            ("99 4111111111111111 5500 0000 0000 0004", vec![(3, 19), (20, 39)]),
+            ("41111111111111112", vec![]),
+            ("1234 5678 9012 3456", vec![]),
+        ];
+
+        for (run, expected) in &cases {
+            let actual: Vec<_> = card_numbers(run).into_iter().map(|card_number| (card_number.start, card_number.end)).collect();
+
+            assert_eq!(expected, &actual, "{}", run);
+        }
+    }
+
+    #[test]
+    fn luhn_check() {
+        assert!(is_luhn_valid("4111-1111-1111-1111"));
+        assert!(is_luhn_valid("5500 0000 0000 0004"));
+        assert!(!is_luhn_valid("4111111111111112"));
+    }
+}
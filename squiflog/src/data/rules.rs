@@ -0,0 +1,381 @@
+// User-defined rules for extracting properties from message text.
+//
+// Rules are written one per line, as a name, an optional set of sources in brackets, and a pattern:
+//
+//   sonicwall [app_name=sonicwall*] id=%{WORD:firewall} sn=%{NOTSPACE:serial} pri=%{INT:priority:int}
+//
+// Patterns are regular expressions that may use grok-style `%{PATTERN:property:type}` references
+// to a library of building blocks, like `%{IP:client}` or `%{NUMBER:duration:float}`. Named captures,
+// like `(?P<user>\w+)`, also become properties.
+
+use std::str::FromStr;
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::{
+    data::selector::Selectors,
+    error::{err_msg, Error},
+};
+
+// Grok references may refer to other references, up to this depth
+const MAX_DEPTH: usize = 8;
+
+// The library of building blocks that can be referenced by grok patterns
+const LIBRARY: &[(&str, &str)] = &[
+    ("USERNAME", r"[a-zA-Z0-9._-]+"),
+    ("USER", r"%{USERNAME}"),
+    ("INT", r"[+-]?[0-9]+"),
+    ("BASE10NUM", r"[+-]?(?:[0-9]+(?:\.[0-9]*)?|\.[0-9]+)"),
+    ("NUMBER", r"%{BASE10NUM}"),
+    ("POSINT", r"\b[1-9][0-9]*\b"),
+    ("NONNEGINT", r"\b[0-9]+\b"),
+    ("WORD", r"\b\w+\b"),
+    ("NOTSPACE", r"\S+"),
+    ("SPACE", r"\s*"),
+    ("DATA", r".*?"),
+    ("GREEDYDATA", r".*"),
+    ("QUOTEDSTRING", r#""(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*'"#),
+    ("UUID", r"[A-Fa-f0-9]{8}-(?:[A-Fa-f0-9]{4}-){3}[A-Fa-f0-9]{12}"),
+    ("MAC", r"(?:[A-Fa-f0-9]{2}[:-]){5}[A-Fa-f0-9]{2}|(?:[A-Fa-f0-9]{4}\.){2}[A-Fa-f0-9]{4}"),
+    ("IPV4", r"(?:(?:25[0-5]|2[0-4][0-9]|1?[0-9]?[0-9])\.){3}(?:25[0-5]|2[0-4][0-9]|1?[0-9]?[0-9])"),
+    // Either all eight groups, or fewer around a `::`, so times like `12:34:56` and a bare `::` don't match
+    ("IPV6", concat!(
+        r"\b(?:[A-Fa-f0-9]{1,4}:){6}%{IPV4}",
+        r"|(?:\b[A-Fa-f0-9]{1,4}(?::[A-Fa-f0-9]{1,4}){0,5})?::(?:[A-Fa-f0-9]{1,4}:){0,5}%{IPV4}",
+        r"|\b(?:[A-Fa-f0-9]{1,4}:){7}[A-Fa-f0-9]{1,4}\b",
+        r"|\b[A-Fa-f0-9]{1,4}(?::[A-Fa-f0-9]{1,4}){0,6}::(?:[A-Fa-f0-9]{1,4}(?::[A-Fa-f0-9]{1,4}){0,6}\b)?",
+        r"|::[A-Fa-f0-9]{1,4}(?::[A-Fa-f0-9]{1,4}){0,6}\b",
+    )),
+    ("IP", r"%{IPV6}|%{IPV4}"),
+    ("HOSTNAME", r"\b[0-9A-Za-z][0-9A-Za-z-]{0,62}(?:\.[0-9A-Za-z][0-9A-Za-z-]{0,62})*\.?\b"),
+    ("IPORHOST", r"%{IP}|%{HOSTNAME}"),
+    ("HOSTPORT", r"%{IPORHOST}:%{POSINT}"),
+    ("UNIXPATH", r"(?:/[^/\s]*)+"),
+    ("URIPATH", r"/[A-Za-z0-9$.+!*'(){},~:;=@#%&_/-]*"),
+    ("EMAILADDRESS", r"[A-Za-z0-9._%+-]+@%{HOSTNAME}"),
+    ("LOGLEVEL", r"(?i:trace|debug|info|notice|warn(?:ing)?|err(?:or)?|crit(?:ical)?|fatal|severe|alert|emerg(?:ency)?)"),
+    ("TIMESTAMP_ISO8601", r"[0-9]{4}-[0-9]{2}-[0-9]{2}[T ][0-9]{2}:[0-9]{2}(?::[0-9]{2}(?:\.[0-9]+)?)?(?:Z|[+-][0-9]{2}:?[0-9]{2})?"),
+    ("HTTPDATE", r"[0-9]{2}/\w{3}/[0-9]{4}:[0-9]{2}:[0-9]{2}:[0-9]{2} [+-][0-9]{4}"),
+    ("SYSLOGTIMESTAMP", r"\w{3} +[0-9]{1,2} [0-9]{2}:[0-9]{2}:[0-9]{2}"),
+];
+
+/**
+The definitions of user-defined parsing rules, in the order they're tried.
+
+Rules are only compiled when the CLEF processor is built.
+*/
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ParseRules(Vec<ParseRule>);
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseRule {
+    name: String,
+    sources: Option<Selectors>,
+    pattern: String,
+}
+
+impl ParseRules {
+    pub(super) fn compile(&self) -> Result<Rules, Error> {
+        self.0.iter().map(ParseRule::compile).collect::<Result<_, _>>().map(Rules)
+    }
+}
+
+impl FromStr for ParseRules {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.lines()
+            .map(str::trim)
+            .filter(|rule| !rule.is_empty() && !rule.starts_with('#'))
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(ParseRules)
+    }
+}
+
+impl ParseRule {
+    fn compile(&self) -> Result<Rule, Error> {
+        let mut captures = vec![];
+        let expanded = expand(&self.pattern, &mut captures, 0)
+            .map_err(|err| err_msg(format!("invalid pattern for parse rule `{}`: {}", self.name, err)))?;
+
+        let regex = Regex::new(&expanded).map_err(|err| err_msg(format!("invalid pattern for parse rule `{}`: {}", self.name, err)))?;
+
+        // Named captures written directly in the pattern are kept as strings
+        for group in regex.capture_names().flatten() {
+            if !captures.iter().any(|capture: &Capture| capture.group == group) {
+                captures.push(Capture {
+                    group: group.to_owned(),
+                    property: group.to_owned(),
+                    coerce: Coerce::String,
+                });
+            }
+        }
+
+        Ok(Rule {
+            name: self.name.clone(),
+            sources: self.sources.clone(),
+            regex,
+            captures,
+        })
+    }
+}
+
+impl FromStr for ParseRule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let name_end = s.find(char::is_whitespace).ok_or_else(|| err_msg(format!("invalid parse rule `{}`, expected a name and pattern", s)))?;
+        let (name, rem) = (&s[..name_end], s[name_end..].trim_start());
+
+        let (sources, pattern) = if let Some(sources) = rem.strip_prefix('[') {
+            let end = sources.find(']').ok_or_else(|| err_msg(format!("unclosed sources for parse rule `{}`", name)))?;
+            (Some(sources[..end].parse()?), sources[end + 1..].trim_start())
+        } else {
+            (None, rem)
+        };
+
+        if pattern.is_empty() {
+            return Err(err_msg(format!("missing pattern for parse rule `{}`", name)));
+        }
+
+        Ok(ParseRule {
+            name: name.to_owned(),
+            sources,
+            pattern: pattern.to_owned(),
+        })
+    }
+}
+
+/**
+Compiled parsing rules.
+*/
+#[derive(Debug, Clone, Default)]
+pub(super) struct Rules(Vec<Rule>);
+
+#[derive(Debug, Clone)]
+struct Rule {
+    name: String,
+    sources: Option<Selectors>,
+    regex: Regex,
+    captures: Vec<Capture>,
+}
+
+#[derive(Debug, Clone)]
+struct Capture {
+    group: String,
+    property: String,
+    coerce: Coerce,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Coerce {
+    String,
+    Int,
+    Float,
+    Bool,
+}
+
+impl Rules {
+    /**
+    Match a message against the rules for its source.
+
+    The name of the first matching rule is returned along with its captured properties.
+    */
+    pub(super) fn captures(&self, message: &str, app_name: Option<&str>, hostname: Option<&str>) -> Option<(&str, Vec<(String, Value)>)> {
+        self.0
+            .iter()
+            .filter(|rule| rule.sources.as_ref().map(|sources| sources.matches(app_name, hostname)).unwrap_or(true))
+            .find_map(|rule| rule.captures(message).map(|captures| (&*rule.name, captures)))
+    }
+}
+
+impl Rule {
+    fn captures(&self, message: &str) -> Option<Vec<(String, Value)>> {
+        let matched = self.regex.captures(message)?;
+
+        let captures = self
+            .captures
+            .iter()
+            .filter_map(|capture| {
+                let value = matched.name(&capture.group)?.as_str();
+                Some((capture.property.clone(), capture.coerce.apply(value)))
+            })
+            .collect();
+
+        Some(captures)
+    }
+}
+
+impl Coerce {
+    // Values that can't be converted are kept as strings
+    fn apply(self, v: &str) -> Value {
+        let converted = match self {
+            Coerce::String => None,
+            Coerce::Int => v.parse::<i64>().ok().map(Value::from),
+            Coerce::Float => v.parse::<f64>().ok().filter(|f| f.is_finite()).map(Value::from),
+            Coerce::Bool => match &*v.to_ascii_lowercase() {
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                _ => None,
+            },
+        };
+
+        converted.unwrap_or_else(|| Value::from(v))
+    }
+}
+
+impl FromStr for Coerce {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "string" => Ok(Coerce::String),
+            "int" => Ok(Coerce::Int),
+            "float" => Ok(Coerce::Float),
+            "bool" => Ok(Coerce::Bool),
+            _ => Err(err_msg(format!("unrecognized type `{}`, expected `string`, `int`, `float`, or `bool`", s))),
+        }
+    }
+}
+
+// Replace grok references like `%{IP:client}` with the regular expressions they stand for
+fn expand(pattern: &str, captures: &mut Vec<Capture>, depth: usize) -> Result<String, Error> {
+    if depth > MAX_DEPTH {
+        return Err(err_msg("grok patterns are nested too deeply"));
+    }
+
+    let mut expanded = String::with_capacity(pattern.len());
+    let mut rem = pattern;
+    while let Some(start) = rem.find("%{") {
+        expanded.push_str(&rem[..start]);
+
+        let reference = &rem[start + 2..];
+        let end = reference.find('}').ok_or_else(|| err_msg("unclosed grok reference"))?;
+
+        let mut parts = reference[..end].splitn(3, ':');
+        let name = parts.next().unwrap_or("");
+        let property = parts.next();
+        let coerce = parts.next().map(str::parse).transpose()?;
+
+        let library = LIBRARY
+            .iter()
+            .find(|(k, _)| *k == name)
+            .map(|(_, v)| v)
+            .ok_or_else(|| err_msg(format!("unknown grok pattern `{}`", name)))?;
+        let library = expand(library, captures, depth + 1)?;
+
+        match property {
+            Some(property) if !property.is_empty() => {
+                // Properties aren't necessarily valid group names, so groups are named by position
+                let group = format!("grok{}", captures.len());
+                expanded.push_str(&format!("(?P<{}>{})", group, library));
+
+                captures.push(Capture {
+                    group,
+                    property: property.to_owned(),
+                    coerce: coerce.unwrap_or(Coerce::String),
+                });
+            }
+            _ => expanded.push_str(&format!("(?:{})", library)),
+        }
+
+        rem = &reference[end + 1..];
+    }
+    expanded.push_str(rem);
+
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn compile(rules: &str) -> Rules {
+        rules.parse::<ParseRules>().expect("failed to parse rules").compile().expect("failed to compile rules")
+    }
+
+    #[test]
+    fn grok_captures_are_coerced() {
+        let rules = compile("sonicwall id=%{WORD:firewall} src=%{IP:src.ip}:%{POSINT:src.port:int} dur=%{NUMBER:duration:float} ok=%{WORD:ok:bool}");
+
+        let (name, captures) = rules
+            .captures("id=firewall src=192.0.2.1:443 dur=1.5 ok=true", None, None)
+            .expect("rule didn't match");
+
+        assert_eq!("sonicwall", name);
+        assert_eq!(vec![
+            ("firewall".to_owned(), json!("firewall")),
+            ("src.ip".to_owned(), json!("192.0.2.1")),
+            ("src.port".to_owned(), json!(443)),
+            ("duration".to_owned(), json!(1.5)),
+            ("ok".to_owned(), json!(true)),
+        ], captures);
+    }
+
+    #[test]
+    fn named_captures_become_properties() {
+        let rules = compile(r"login ^user (?P<user>\w+) logged in from %{IPORHOST:from}$");
+
+        let (_, captures) = rules.captures("user alice logged in from host1.example.com", None, None).expect("rule didn't match");
+
+        assert_eq!(vec![
+            ("from".to_owned(), json!("host1.example.com")),
+            ("user".to_owned(), json!("alice")),
+        ], captures);
+    }
+
+    #[test]
+    fn rules_are_tried_in_order_for_their_sources() {
+        let rules = compile("
+            # Rules for the firewall
+            firewall [app_name=fw*] ^action=%{WORD:action}
+            any ^%{WORD:first}
+        ");
+
+        assert_eq!("firewall", rules.captures("action=allow", Some("fw1"), None).expect("rule didn't match").0);
+        assert_eq!("any", rules.captures("action=allow", Some("web"), None).expect("rule didn't match").0);
+        assert!(rules.captures("!", Some("web"), None).is_none());
+    }
+
+    #[test]
+    fn ip_matches_ipv6_addresses() {
+        let rules = compile("ip %{IP:ip}");
+
+        for ip in &["2001:db8:0:0:1:0:0:1", "2001:db8::1", "fe80::", "::1", "::ffff:192.0.2.1", "2001:db8::192.0.2.1", "192.0.2.1"] {
+            let (_, captures) = rules.captures(&format!("from {} port 80", ip), None, None).expect("rule didn't match");
+            assert_eq!(vec![("ip".to_owned(), json!(ip))], captures);
+        }
+    }
+
+    #[test]
+    fn ip_does_not_match_times_or_separators() {
+        let rules = compile("ip %{IP:ip}");
+
+        for text in &["uptime 12:34:56", "mac 00:1a:2b:3c:4d:5e", "key::value", "error: ::"] {
+            assert!(rules.captures(text, None, None).is_none(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn uncoercible_values_are_kept_as_strings() {
+        assert_eq!(json!("12a"), Coerce::Int.apply("12a"));
+        assert_eq!(json!("yes"), Coerce::Bool.apply("yes"));
+        assert_eq!(json!(-12), Coerce::Int.apply("-12"));
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        for rules in &["nopattern", "name [app_name=a", "name [app_name=a]", "name [proc_id=1] .*"] {
+            assert!(rules.parse::<ParseRules>().is_err(), "{}", rules);
+        }
+
+        for rules in &["name %{UNKNOWN:a}", "name %{INT:a:decimal}", "name %{INT:a", "name (unclosed"] {
+            let parsed = rules.parse::<ParseRules>().expect("failed to parse rules");
+            assert!(parsed.compile().is_err(), "{}", rules);
+        }
+    }
+}
@@ -0,0 +1,411 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+};
+
+use serde_json::{
+    json,
+    Map,
+    Value,
+};
+
+use crate::data::{
+    syslog::StructuredDataElement,
+    Config,
+    StructuredDataFormat,
+};
+
+/**
+Convert a SYSLOG structured data element into CLEF properties.
+
+The SD-IDs registered with IANA by RFC5424 (`timeQuality`, `origin`, and `meta`)
+can be decoded into typed properties. Other elements are represented according to the
+configured format, optionally with their enterprise number split from their name
+and their param values converted into numbers and booleans.
+*/
+pub(super) fn into_clef<'a>(
+    element: StructuredDataElement<'a>,
+    config: &Config,
+    additional: &mut HashMap<Cow<'a, str>, Value>,
+) {
+    if config.decode_registered_sd_ids {
+        match element.id {
+            "timeQuality" => return time_quality_into_clef(element, additional),
+            "origin" => return origin_into_clef(element, additional),
+            "meta" => return meta_into_clef(element, additional),
+            _ => (),
+        }
+    }
+
+    let mut id = element.id;
+    if config.split_sd_enterprise_ids {
+        if let Some((name, enterprise_id)) = split_enterprise_id(element.id) {
+            // Keep the qualified id if another enterprise has already used the name.
+            // Flattened elements only add their params, so also look for the enterprise id.
+            let enterprise_id_key = format!("{}_enterprise_id", name);
+            if !additional.contains_key(name) && !additional.contains_key(enterprise_id_key.as_str()) {
+                id = name;
+                additional.insert(Cow::Owned(enterprise_id_key), json!(enterprise_id));
+            }
+        }
+    }
+
+    let infer_types = config.infer_sd_param_types;
+
+    match config.structured_data_format {
+        StructuredDataFormat::List => {
+            let mut params = vec![];
+            for (k, v) in element.params {
+                let mut map = HashMap::new();
+                map.insert(k, param_value(v, infer_types));
+                params.push(map);
+            }
+            additional.insert(Cow::Borrowed(id), json!(params));
+        }
+        StructuredDataFormat::Object => {
+            let mut params = Map::new();
+            for (k, v) in element.params {
+                insert_param(&mut params, k, param_value(v, infer_types));
+            }
+            additional.insert(Cow::Borrowed(id), Value::Object(params));
+        }
+        StructuredDataFormat::Flatten => {
+            for (k, v) in element.params {
+                let key = Cow::Owned(format!("{}.{}", id, k));
+                let v = param_value(v, infer_types);
+                match additional.get_mut(&key) {
+                    Some(existing) => append_repeated_param(existing, v),
+                    None => {
+                        additional.insert(key, v);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/**
+Split an SD-ID like `exampleSDID@32473` into its name and private enterprise number.
+*/
+pub(super) fn split_enterprise_id(id: &str) -> Option<(&str, &str)> {
+    let at = id.find('@')?;
+    let (name, enterprise_id) = (&id[..at], &id[at + 1..]);
+
+    // Enterprise numbers may carry sub-identifiers, like `32473.1.2`
+    let is_enterprise_id = !enterprise_id.is_empty()
+        && enterprise_id.split('.').all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()));
+
+    if name.is_empty() || !is_enterprise_id {
+        None
+    } else {
+        Some((name, enterprise_id))
+    }
+}
+
+/**
+Get the CLEF value for a param, converting it into a number or boolean if it looks like one.
+*/
+fn param_value(v: String, infer_types: bool) -> Value {
+    if !infer_types {
+        return Value::String(v);
+    }
+
+    match &*v {
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        _ => (),
+    }
+
+    // Leading zeros and signs suggest identifiers rather than quantities
+    let digits = v.strip_prefix('-').unwrap_or(&v);
+    let looks_numeric = digits.bytes().next().map(|b| b.is_ascii_digit()).unwrap_or(false)
+        && !(digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0."));
+
+    if looks_numeric {
+        if let Ok(n) = v.parse::<i64>() {
+            return json!(n);
+        }
+        if let Ok(n) = v.parse::<u64>() {
+            return json!(n);
+        }
+
+        // Integers too long for either are kept whole, rather than rounded into a float
+        let is_float = v.contains(&['.', 'e', 'E'][..]);
+        if is_float {
+            if let Ok(n) = v.parse::<f64>() {
+                if n.is_finite() {
+                    return json!(n);
+                }
+            }
+        }
+    }
+
+    Value::String(v)
+}
+
+// Collapse the values of a repeated param into an array
+fn append_repeated_param(existing: &mut Value, value: Value) {
+    match existing {
+        Value::Array(values) => values.push(value),
+        existing => {
+            let first = existing.take();
+            *existing = json!([first, value]);
+        }
+    }
+}
+
+/**
+The first IP address of the `origin` element, if there is one.
+*/
+pub(super) fn origin_ip(element: &StructuredDataElement) -> Option<String> {
+    if element.id != "origin" {
+        return None;
+    }
+
+    element
+        .params
+        .iter()
+        .find(|(k, _)| *k == "ip")
+        .map(|(_, v)| v.clone())
+}
+
+// RFC5424 7.1: `[timeQuality tzKnown="1" isSynced="1" syncAccuracy="60000"]`
+fn time_quality_into_clef<'a>(element: StructuredDataElement<'a>, additional: &mut HashMap<Cow<'a, str>, Value>) {
+    let mut time_quality = Map::new();
+    for (k, v) in element.params {
+        match k {
+            "tzKnown" => insert_param(&mut time_quality, "tz_known", flag(v)),
+            "isSynced" => insert_param(&mut time_quality, "is_synced", flag(v)),
+            "syncAccuracy" => insert_param(&mut time_quality, "sync_accuracy", number(v)),
+            _ => insert_param(&mut time_quality, k, Value::String(v)),
+        }
+    }
+    additional.insert(Cow::Borrowed("time_quality"), Value::Object(time_quality));
+}
+
+// RFC5424 7.2: `[origin ip="192.0.2.1" ip="192.0.2.129" enterpriseId="32473" software="x" swVersion="1.0"]`
+fn origin_into_clef<'a>(element: StructuredDataElement<'a>, additional: &mut HashMap<Cow<'a, str>, Value>) {
+    let mut origin = Map::new();
+    for (k, v) in element.params {
+        match k {
+            "ip" => insert_param(&mut origin, "ip", Value::String(v)),
+            // Enterprise ids may carry sub-identifiers, like `32473.1.2`
+            "enterpriseId" => insert_param(&mut origin, "enterprise_id", Value::String(v)),
+            "software" => insert_param(&mut origin, "software", Value::String(v)),
+            "swVersion" => insert_param(&mut origin, "sw_version", Value::String(v)),
+            _ => insert_param(&mut origin, k, Value::String(v)),
+        }
+    }
+    additional.insert(Cow::Borrowed("origin"), Value::Object(origin));
+}
+
+// RFC5424 7.3: `[meta sequenceId="29" sysUpTime="1234" language="en-US"]`
+fn meta_into_clef<'a>(element: StructuredDataElement<'a>, additional: &mut HashMap<Cow<'a, str>, Value>) {
+    let mut meta = Map::new();
+    for (k, v) in element.params {
+        match k {
+            "sequenceId" => insert_param(&mut meta, "sequence_id", number(v)),
+            "sysUpTime" => insert_param(&mut meta, "sys_up_time", number(v)),
+            "language" => insert_param(&mut meta, "language", Value::String(v)),
+            _ => insert_param(&mut meta, k, Value::String(v)),
+        }
+    }
+    additional.insert(Cow::Borrowed("meta"), Value::Object(meta));
+}
+
+fn insert_param(params: &mut Map<String, Value>, k: &str, v: Value) {
+    match params.get_mut(k) {
+        Some(existing) => append_repeated_param(existing, v),
+        None => {
+            params.insert(k.to_owned(), v);
+        }
+    }
+}
+
+// `1` and `0` flags, as used by `timeQuality`
+fn flag(v: String) -> Value {
+    match &*v {
+        "1" => Value::Bool(true),
+        "0" => Value::Bool(false),
+        _ => Value::String(v),
+    }
+}
+
+// Non-negative integers, as used by `timeQuality` and `meta`
+fn number(v: String) -> Value {
+    match v.parse::<u64>() {
+        Ok(n) => json!(n),
+        Err(_) => Value::String(v),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_clef(id: &'static str, params: Vec<(&'static str, &str)>) -> Value {
+        let element = StructuredDataElement {
+            id,
+            params: params.into_iter().map(|(k, v)| (k, v.to_owned())).collect(),
+        };
+
+        let config = Config {
+            decode_registered_sd_ids: true,
+            ..Default::default()
+        };
+
+        let mut additional = HashMap::new();
+        into_clef(element, &config, &mut additional);
+
+        json!(additional)
+    }
+
+    #[test]
+    fn time_quality_is_typed() {
+        let actual = to_clef("timeQuality", vec![("tzKnown", "1"), ("isSynced", "0"), ("syncAccuracy", "60000")]);
+
+        assert_eq!(json!({
+            "time_quality": { "tz_known": true, "is_synced": false, "sync_accuracy": 60000 }
+        }), actual);
+    }
+
+    #[test]
+    fn origin_is_typed() {
+        let actual = to_clef("origin", vec![
+            ("ip", "192.0.2.1"),
+            ("ip", "192.0.2.129"),
+            ("enterpriseId", "32473.1"),
+            ("software", "test"),
+            ("swVersion", "1.0"),
+        ]);
+
+        assert_eq!(json!({
+            "origin": {
+                "ip": ["192.0.2.1", "192.0.2.129"],
+                "enterprise_id": "32473.1",
+                "software": "test",
+                "sw_version": "1.0"
+            }
+        }), actual);
+    }
+
+    #[test]
+    fn meta_is_typed() {
+        let actual = to_clef("meta", vec![("sequenceId", "29"), ("sysUpTime", "1234"), ("language", "en-US")]);
+
+        assert_eq!(json!({
+            "meta": { "sequence_id": 29, "sys_up_time": 1234, "language": "en-US" }
+        }), actual);
+    }
+
+    #[test]
+    fn invalid_registered_values_are_kept_as_strings() {
+        let actual = to_clef("timeQuality", vec![("tzKnown", "yes"), ("syncAccuracy", "-1"), ("extra", "1")]);
+
+        assert_eq!(json!({
+            "time_quality": { "tz_known": "yes", "sync_accuracy": "-1", "extra": "1" }
+        }), actual);
+    }
+
+    #[test]
+    fn origin_ip_is_first_ip() {
+        let element = StructuredDataElement {
+            id: "origin",
+            params: vec![("software", "test".to_owned()), ("ip", "192.0.2.1".to_owned()), ("ip", "192.0.2.129".to_owned())],
+        };
+
+        assert_eq!(Some("192.0.2.1".to_owned()), origin_ip(&element));
+    }
+
+    #[test]
+    fn enterprise_ids_are_split() {
+        assert_eq!(Some(("exampleSDID", "32473")), split_enterprise_id("exampleSDID@32473"));
+        assert_eq!(Some(("exampleSDID", "32473.1.2")), split_enterprise_id("exampleSDID@32473.1.2"));
+
+        for id in &["exampleSDID", "@32473", "exampleSDID@", "exampleSDID@abc", "exampleSDID@32473..1"] {
+            assert_eq!(None, split_enterprise_id(id), "{}", id);
+        }
+    }
+
+    #[test]
+    fn enterprise_qualified_elements_are_split() {
+        let config = Config {
+            structured_data_format: StructuredDataFormat::Object,
+            split_sd_enterprise_ids: true,
+            ..Default::default()
+        };
+
+        let mut additional = HashMap::new();
+        for id in &["exampleSDID@32473", "exampleSDID@40000"] {
+            let element = StructuredDataElement {
+                id,
+                params: vec![("iut", "3".to_owned())],
+            };
+            into_clef(element, &config, &mut additional);
+        }
+
+        assert_eq!(json!({
+            "exampleSDID": { "iut": "3" },
+            "exampleSDID_enterprise_id": "32473",
+            "exampleSDID@40000": { "iut": "3" },
+        }), json!(additional));
+    }
+
+    #[test]
+    fn flattened_enterprise_qualified_elements_are_split() {
+        let config = Config {
+            structured_data_format: StructuredDataFormat::Flatten,
+            split_sd_enterprise_ids: true,
+            ..Default::default()
+        };
+
+        let mut additional = HashMap::new();
+        for (id, iut) in &[("exampleSDID@32473", "3"), ("exampleSDID@40000", "4")] {
+            let element = StructuredDataElement {
+                id,
+                params: vec![("iut", iut.to_string())],
+            };
+            into_clef(element, &config, &mut additional);
+        }
+
+        assert_eq!(json!({
+            "exampleSDID.iut": "3",
+            "exampleSDID_enterprise_id": "32473",
+            "exampleSDID@40000.iut": "4",
+        }), json!(additional));
+    }
+
+    #[test]
+    fn param_types_are_inferred() {
+        let cases = vec![
+            ("3", json!(3)),
+            ("-42", json!(-42)),
+            ("18446744073709551615", json!(18446744073709551615u64)),
+            ("0", json!(0)),
+            ("0.5", json!(0.5)),
+            ("1.5e3", json!(1500.0)),
+            ("true", json!(true)),
+            ("false", json!(false)),
+            ("007", json!("007")),
+            ("1e400", json!("1e400")),
+            ("NaN", json!("NaN")),
+            ("inf", json!("inf")),
+            ("3 apples", json!("3 apples")),
+            ("True", json!("True")),
+            ("", json!("")),
+            ("-", json!("-")),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(expected, param_value(input.to_owned(), true), "{}", input);
+            assert_eq!(json!(input), param_value(input.to_owned(), false), "{}", input);
+        }
+    }
+
+    #[test]
+    fn long_integers_are_kept_as_strings() {
+        for input in &["12345678901234567890123", "18446744073709551616", "-9223372036854775809"] {
+            assert_eq!(json!(input), param_value(input.to_string(), true), "{}", input);
+        }
+    }
+}
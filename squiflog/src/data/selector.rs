@@ -0,0 +1,181 @@
+use std::str::FromStr;
+
+use crate::error::{err_msg, Error};
+
+/**
+A set of selectors, where a message matches if any one of them matches.
+
+Selectors are separated by `;`, like `app_name=fortigate;hostname=mikrotik-*`.
+*/
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Selectors(Vec<Selector>);
+
+impl Selectors {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn matches(&self, app_name: Option<&str>, hostname: Option<&str>) -> bool {
+        self.0.iter().any(|selector| selector.matches(app_name, hostname))
+    }
+}
+
+impl FromStr for Selectors {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(';')
+            .map(str::trim)
+            .filter(|selector| !selector.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(Selectors)
+    }
+}
+
+/**
+Select messages by their SYSLOG app name and hostname.
+
+Selectors are a comma-separated list of conditions that must all match, like
+`app_name=fortigate,hostname=fw-*`, where `*` matches any sequence of characters.
+The selector `*` matches every message.
+*/
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Selector {
+    app_name: Option<Pattern>,
+    hostname: Option<Pattern>,
+}
+
+impl Selector {
+    pub fn any() -> Self {
+        Selector::default()
+    }
+
+    pub fn matches(&self, app_name: Option<&str>, hostname: Option<&str>) -> bool {
+        fn is_match(pattern: &Option<Pattern>, value: Option<&str>) -> bool {
+            match (pattern, value) {
+                (None, _) => true,
+                (Some(pattern), Some(value)) => pattern.matches(value),
+                (Some(_), None) => false,
+            }
+        }
+
+        is_match(&self.app_name, app_name) && is_match(&self.hostname, hostname)
+    }
+}
+
+impl FromStr for Selector {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut selector = Selector::any();
+
+        if s.trim() == "*" {
+            return Ok(selector);
+        }
+
+        for condition in s.split(',') {
+            let condition = condition.trim();
+            let eq = condition
+                .find('=')
+                .ok_or_else(|| err_msg(format!("invalid selector `{}`, expected `app_name=` or `hostname=`", condition)))?;
+
+            let pattern = Some(Pattern(condition[eq + 1..].trim().to_ascii_lowercase()));
+            match condition[..eq].trim() {
+                "app_name" => selector.app_name = pattern,
+                "hostname" => selector.hostname = pattern,
+                field => return Err(err_msg(format!("unrecognized selector field `{}`, expected `app_name` or `hostname`", field))),
+            }
+        }
+
+        Ok(selector)
+    }
+}
+
+// A case-insensitive pattern, where `*` matches any sequence of characters.
+// The pattern itself is stored in lowercase.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Pattern(String);
+
+impl Pattern {
+    fn matches(&self, value: &str) -> bool {
+        let value = value.to_ascii_lowercase();
+
+        let mut parts = self.0.split('*');
+        let first = parts.next().unwrap_or("");
+        if !value.starts_with(first) {
+            return false;
+        }
+
+        let mut rem = &value[first.len()..];
+        let mut parts = parts.peekable();
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                // The last part must match the end of the value
+                return rem.len() >= part.len() && rem.ends_with(part);
+            }
+
+            match rem.find(part) {
+                Some(idx) => rem = &rem[idx + part.len()..],
+                None => return false,
+            }
+        }
+
+        // There were no wildcards, so the value must match exactly
+        rem.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_match() {
+        let cases = [
+            ("fortigate", "fortigate", true),
+            ("fortigate", "FortiGate", true),
+            ("fortigate", "fortigate2", false),
+            ("fw-*", "fw-01", true),
+            ("fw-*", "fw-", true),
+            ("fw-*", "web-01", false),
+            ("*.example.com", "fw.example.com", true),
+            ("*.example.com", "fw.example.org", false),
+            ("a*b*c", "abc", true),
+            ("a*b*c", "a-b-c", true),
+            ("a*b*c", "a-c", false),
+            ("ab*ba", "aba", false),
+            ("*", "anything", true),
+        ];
+
+        for (pattern, value, expected) in &cases {
+            assert_eq!(*expected, Pattern(pattern.to_string()).matches(value), "{} {}", pattern, value);
+        }
+    }
+
+    #[test]
+    fn selectors_match() {
+        let selectors: Selectors = "app_name=fortigate; hostname=mikrotik-*, app_name=router".parse().unwrap();
+
+        assert!(selectors.matches(Some("fortigate"), None));
+        assert!(selectors.matches(Some("router"), Some("mikrotik-01")));
+        assert!(!selectors.matches(Some("router"), Some("cisco-01")));
+        assert!(!selectors.matches(None, Some("mikrotik-01")));
+        assert!(!Selectors::default().matches(Some("fortigate"), None));
+    }
+
+    #[test]
+    fn any_selector_matches_everything() {
+        let selectors: Selectors = "*".parse().unwrap();
+
+        assert!(selectors.matches(None, None));
+        assert!(selectors.matches(Some("app"), Some("host")));
+    }
+
+    #[test]
+    fn invalid_selectors_are_rejected() {
+        for selector in &["app_name", "proc_id=1", "app_name=a,host"] {
+            assert!(selector.parse::<Selector>().is_err(), "{}", selector);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+// Clock skew between when messages say they were sent and when they were received.
+//
+// Skew is tracked per hostname so devices with misconfigured clocks can be found through
+// diagnostics. Positive skew means a message's timestamp is behind the time it was received,
+// and negative skew means it's ahead.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use chrono::Duration;
+
+use crate::diagnostics::{Level, MIN_LEVEL};
+
+// Hosts beyond this many in a single metrics interval aren't tracked
+const MAX_HOSTS: usize = 1000;
+
+lazy_static! {
+    static ref SKEW: Mutex<HashMap<String, Stats>> = Mutex::new(HashMap::new());
+}
+
+/**
+Clock skew statistics for a host over a metrics interval.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub(crate) struct Stats {
+    count: usize,
+    min_ms: i64,
+    max_ms: i64,
+    mean_ms: i64,
+    #[serde(skip)]
+    total_ms: i64,
+}
+
+impl Stats {
+    fn new(skew_ms: i64) -> Self {
+        Stats {
+            count: 1,
+            min_ms: skew_ms,
+            max_ms: skew_ms,
+            mean_ms: skew_ms,
+            total_ms: skew_ms,
+        }
+    }
+
+    fn record(&mut self, skew_ms: i64) {
+        self.count += 1;
+        self.min_ms = self.min_ms.min(skew_ms);
+        self.max_ms = self.max_ms.max(skew_ms);
+        self.total_ms = self.total_ms.saturating_add(skew_ms);
+        self.mean_ms = self.total_ms / self.count as i64;
+    }
+}
+
+/**
+Record the skew of a message received from a host.
+*/
+pub(super) fn record(hostname: Option<&str>, skew: Duration) {
+    if !MIN_LEVEL.includes(Level::Debug) {
+        return;
+    }
+
+    let mut hosts = SKEW.lock().expect("skew lock poisoned");
+    record_into(&mut hosts, hostname.unwrap_or("-"), skew.num_milliseconds());
+}
+
+/**
+Take the skew statistics recorded for each host since the last call.
+*/
+pub(crate) fn take() -> HashMap<String, Stats> {
+    std::mem::take(&mut *SKEW.lock().expect("skew lock poisoned"))
+}
+
+fn record_into(hosts: &mut HashMap<String, Stats>, hostname: &str, skew_ms: i64) {
+    let is_full = hosts.len() >= MAX_HOSTS;
+
+    match hosts.get_mut(hostname) {
+        Some(stats) => stats.record(skew_ms),
+        None if !is_full => {
+            hosts.insert(hostname.to_owned(), Stats::new(skew_ms));
+        }
+        None => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_are_tracked_per_host() {
+        let mut hosts = HashMap::new();
+
+        record_into(&mut hosts, "host1", 100);
+        record_into(&mut hosts, "host1", -50);
+        record_into(&mut hosts, "host1", 250);
+        record_into(&mut hosts, "host2", 0);
+
+        let host1 = hosts["host1"];
+        assert_eq!((3, -50, 250, 100), (host1.count, host1.min_ms, host1.max_ms, host1.mean_ms));
+        assert_eq!(1, hosts["host2"].count);
+    }
+}
@@ -0,0 +1,83 @@
+// Several messages packed into a single datagram, separated by newlines or NULs:
+//
+//   <34>Oct 11 22:14:15 host1 su: 'su root' failed\n<35>Oct 11 22:14:16 host1 su: 'su root' succeeded\0
+//
+// A separator only ends a message when it's followed by the `<PRI>` of the next one, so
+// messages with newlines in their text aren't split. Trailing NUL, CR, and LF bytes are trimmed.
+
+use crate::data::parsers;
+
+/**
+Split a datagram into the messages it contains.
+*/
+pub(super) fn messages(datagram: &[u8]) -> Vec<&[u8]> {
+    let mut messages = vec![];
+
+    let mut start = 0;
+    let mut i = 0;
+    while i < datagram.len() {
+        if is_separator(datagram[i]) {
+            let next = i + datagram[i..].iter().take_while(|b| is_separator(**b)).count();
+
+            if is_priority(&datagram[next..]) {
+                messages.extend(trim(&datagram[start..i]));
+                start = next;
+            }
+
+            i = next;
+        } else {
+            i += 1;
+        }
+    }
+
+    messages.extend(trim(&datagram[start..]));
+    messages
+}
+
+fn is_separator(b: u8) -> bool {
+    b == b'\n' || b == b'\r' || b == b'\0'
+}
+
+// `<34>`, where the largest PRI is `<191>`
+fn is_priority(i: &[u8]) -> bool {
+    match parsers::priority(i) {
+        Ok((priority, _)) => priority <= 191,
+        Err(_) => false,
+    }
+}
+
+fn trim(mut msg: &[u8]) -> Option<&[u8]> {
+    while let Some((last, rest)) = msg.split_last() {
+        if !is_separator(*last) {
+            break;
+        }
+        msg = rest;
+    }
+
+    if msg.is_empty() {
+        None
+    } else {
+        Some(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_on_separators_before_priority() {
+        let cases: [(&[u8], &[&[u8]]); 6] = [
+            (b"<34>first\n<35>second\n", &[b"<34>first", b"<35>second"]),
+            (b"<34>first\r\n<35>second\r\n", &[b"<34>first", b"<35>second"]),
+            (b"<34>first\0<35>second\0\0", &[b"<34>first", b"<35>second"]),
+            (b"<34>first\nline two\n<35>second", &[b"<34>first\nline two", b"<35>second"]),
+            (b"<34>first\n<not a priority>\n<999>", &[b"<34>first\n<not a priority>\n<999>"]),
+            (b"\0\n", &[]),
+        ];
+
+        for (datagram, expected) in &cases {
+            assert_eq!(expected.to_vec(), messages(datagram), "{:?}", String::from_utf8_lossy(datagram));
+        }
+    }
+}
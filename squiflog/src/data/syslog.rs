@@ -3,9 +3,12 @@ use crate::{
         err_msg,
         Error,
     },
-    data::parsers
+    data::{
+        charset::Charsets,
+        parsers,
+    },
 };
-use std::borrow::Cow;
+use std::{borrow::Cow, str::FromStr};
 use chrono::{Utc, DateTime};
 
 #[derive(Debug, Eq, PartialEq)]
@@ -72,10 +75,88 @@ pub struct StructuredDataElement<'a> {
     pub params: Vec<(&'a str, String)>,
 }
 
+/**
+How received messages are matched against the SYSLOG formats.
+*/
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ParseMode {
+    /**
+    Try RFC5424, falling back to RFC3164 for anything else.
+    */
+    Auto,
+    /**
+    Like `Auto`, but report why a message that declared itself
+    as RFC5424 had to fall back to RFC3164.
+    */
+    Lenient,
+    /**
+    Only accept RFC5424 messages.
+    */
+    Strict5424,
+    /**
+    Only accept RFC3164 messages with a priority, timestamp, and hostname.
+    */
+    Strict3164,
+}
+
+impl Default for ParseMode {
+    fn default() -> Self {
+        ParseMode::Auto
+    }
+}
+
+impl FromStr for ParseMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ParseMode::Auto),
+            "lenient" => Ok(ParseMode::Lenient),
+            "strict-5424" => Ok(ParseMode::Strict5424),
+            "strict-3164" => Ok(ParseMode::Strict3164),
+            _ => Err(err_msg("expected `auto`, `lenient`, `strict-5424`, or `strict-3164`")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Format {
+    Rfc5424,
+    Rfc3164,
+}
+
+impl Format {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Rfc5424 => "RFC5424",
+            Format::Rfc3164 => "RFC3164",
+        }
+    }
+}
+
+/**
+The reason a message couldn't be parsed in the expected format.
+*/
+#[derive(Debug, Eq, PartialEq)]
+pub struct Rejection {
+    pub format: Format,
+    pub reason: String,
+}
+
+impl Rejection {
+    fn new(format: Format, reason: Error) -> Self {
+        Rejection {
+            format,
+            reason: reason.to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct Message<'a> {
     pub priority: Priority,
     pub timestamp: Option<DateTime<Utc>>,
+    pub timestamp_text: Option<&'a str>,
     pub hostname: Option<&'a str>,
     pub app_name: Option<&'a str>,
     pub proc_id: Option<&'a str>,
@@ -85,21 +166,136 @@ pub struct Message<'a> {
 }
 
 impl<'a> Message<'a> {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &'a str) -> Self {
         Self::from_bytes(s.as_bytes())
     }
 
     pub fn from_bytes(s: &'a [u8]) -> Self {
-        Self::from_rfc5424_bytes(s).unwrap_or_else(|_| Self::from_rfc3164_bytes(s, &Utc::now()))
+        Self::from_bytes_with_mode(s, ParseMode::Auto, &Utc::now()).0
+    }
+
+    /**
+    Parse a SYSLOG message according to the given mode.
+
+    If the message doesn't satisfy the format required by the mode then the reason
+    is returned alongside whatever could be recovered from it.
+    */
+    pub fn from_bytes_with_mode(s: &'a [u8], mode: ParseMode, now: &DateTime<Utc>) -> (Self, Option<Rejection>) {
+        Self::from_bytes_with_charsets(s, mode, &Charsets::default(), false, false, now)
+    }
+
+    /**
+    Parse a SYSLOG message according to the given mode, decoding message text that isn't
+    valid UTF-8 with the given charsets.
+
+    RFC5424 message text is trimmed, unless `keep_indentation` is set, in which case
+    only its trailing whitespace is. RFC3164 messages only take their app name and
+    process id from their tag if `parse_tags` is set.
+    */
+    pub fn from_bytes_with_charsets(
+        s: &'a [u8],
+        mode: ParseMode,
+        charsets: &Charsets,
+        keep_indentation: bool,
+        parse_tags: bool,
+        now: &DateTime<Utc>,
+    ) -> (Self, Option<Rejection>) {
+        match mode {
+            ParseMode::Auto => {
+                let msg = Self::rfc5424(s, charsets, keep_indentation).unwrap_or_else(|_| Self::rfc3164(s, charsets, parse_tags, now));
+                (msg, None)
+            }
+            ParseMode::Lenient => match Self::rfc5424(s, charsets, keep_indentation) {
+                Ok(msg) => (msg, None),
+                Err(err) => {
+                    // Only messages that claim to be RFC5424 are worth reporting on
+                    let rejection = if is_rfc5424_header(s) {
+                        Some(Rejection::new(Format::Rfc5424, err))
+                    } else {
+                        None
+                    };
+
+                    (Self::rfc3164(s, charsets, parse_tags, now), rejection)
+                }
+            },
+            ParseMode::Strict5424 => match Self::rfc5424(s, charsets, keep_indentation) {
+                Ok(msg) => (msg, None),
+                Err(err) => (Self::raw(s, charsets, now), Some(Rejection::new(Format::Rfc5424, err))),
+            },
+            ParseMode::Strict3164 => match Self::strict_rfc3164(s, charsets, parse_tags, now) {
+                Ok(msg) => (msg, None),
+                Err(err) => (Self::raw(s, charsets, now), Some(Rejection::new(Format::Rfc3164, err))),
+            },
+        }
+    }
+
+    // The whole input as the message, without interpreting any header
+    fn raw(msg: &'a [u8], charsets: &Charsets, now: &DateTime<Utc>) -> Self {
+        let trimmed = trim_bytes(msg);
+
+        Message {
+            priority: Priority::from_raw(13),
+            timestamp: Some(*now),
+            timestamp_text: None,
+            hostname: None,
+            app_name: None,
+            proc_id: None,
+            message_id: None,
+            structured_data: None,
+            message: if !trimmed.is_empty() { Some(charsets.decode(trimmed, None, None)) } else { None },
+        }
+    }
+
+    // RFC3164 format, requiring each of the PRIVAL, TIMESTAMP and HOSTNAME to be present.
+    pub fn from_strict_rfc3164_bytes(msg: &'a [u8], now: &DateTime<Utc>) -> Result<Self, Error> {
+        Self::strict_rfc3164(msg, &Charsets::default(), false, now)
+    }
+
+    fn strict_rfc3164(msg: &'a [u8], charsets: &Charsets, parse_tags: bool, now: &DateTime<Utc>) -> Result<Self, Error> {
+        let (priority, ts_start) = parsers::priority(msg)?;
+        let (timestamp, rem) = parsers::loose_timestamp(ts_start, now)?;
+        let timestamp_text = consumed(ts_start, rem);
+        let (_, rem) = parsers::byte(rem, b' ')?;
+        let (hostname, rem) = parsers::header_item(rem, "hostname")?;
+
+        if hostname.is_none() {
+            return Err(err_msg("missing hostname"));
+        }
+
+        // The tag is always used to select a charset, even if it isn't kept as the app name
+        let tag = parsers::tag(rem).ok().map(|(tag, _)| tag);
+        let (app_name, proc_id) = match tag {
+            Some((app_name, proc_id)) if parse_tags => (Some(app_name), proc_id),
+            _ => (None, None),
+        };
+
+        Ok(Message {
+            priority: Priority::from_raw(priority),
+            timestamp: Some(timestamp),
+            timestamp_text,
+            hostname,
+            app_name,
+            proc_id,
+            message_id: None,
+            structured_data: None,
+            message: if !rem.is_empty() { Some(charsets.decode(rem, tag.map(|(app_name, _)| app_name), hostname)) } else { None },
+        })
     }
 
     // RFC3164 format: <PRIVAL>TIMESTAMP HOSTNAME TAG: (MSG)
-    // We treat the tag as part of the message.
+    // The tag is kept as part of the message, but may also provide the app name and process id.
     pub fn from_rfc3164_bytes(msg: &'a [u8], now: &DateTime<Utc>) -> Self {
+        Self::rfc3164(msg, &Charsets::default(), false, now)
+    }
+
+    fn rfc3164(msg: &'a [u8], charsets: &Charsets, parse_tags: bool, now: &DateTime<Utc>) -> Self {
         let mut unparsed = msg;
+        let mut tag = None;
         let mut result = Message {
             priority: Priority::from_raw(13),
             timestamp: None,
+            timestamp_text: None,
             hostname: None,
             app_name: None,
             proc_id: None,
@@ -114,6 +310,7 @@ impl<'a> Message<'a> {
 
             if let Ok((timestamp, rem)) = parsers::loose_timestamp(unparsed, now) {
                 result.timestamp = Some(timestamp);
+                result.timestamp_text = consumed(unparsed, rem);
                 unparsed = rem;
 
                 if let Ok((_, rem)) = parsers::byte(unparsed, b' ') {
@@ -122,15 +319,21 @@ impl<'a> Message<'a> {
                     if let Ok((hostname, rem)) = parsers::header_item(unparsed, "hostname") {
                         result.hostname = hostname;
                         unparsed = rem;
+
+                        tag = parsers::tag(unparsed).ok().map(|(tag, _)| tag);
+                        if let (true, Some((app_name, proc_id))) = (parse_tags, tag) {
+                            result.app_name = Some(app_name);
+                            result.proc_id = proc_id;
+                        }
                     }
                 }
             }
         }
 
-        result.message = if unparsed.len() > 0 { Some(String::from_utf8_lossy(unparsed)) } else { None };
+        result.message = if !unparsed.is_empty() { Some(charsets.decode(unparsed, tag.map(|(app_name, _)| app_name), result.hostname)) } else { None };
 
         if result.timestamp.is_none() {
-            result.timestamp = Some(now.clone())
+            result.timestamp = Some(*now)
         }
 
         result
@@ -138,11 +341,16 @@ impl<'a> Message<'a> {
 
     // RFC5424 format: <PRIVAL>VERSION TIMESTAMP HOSTNAME APP-NAME PROCID MSGID STRUCTURED-DATA (MSG)
     pub fn from_rfc5424_bytes(msg: &'a [u8]) -> Result<Self, Error> {
+        Self::rfc5424(msg, &Charsets::default(), false)
+    }
+
+    fn rfc5424(msg: &'a [u8], charsets: &Charsets, keep_indentation: bool) -> Result<Self, Error> {
         let (priority, rem) = parsers::priority(msg)?;
 
         let mut result = Message {
             priority: Priority::from_raw(priority),
             timestamp: None,
+            timestamp_text: None,
             hostname: None,
             app_name: None,
             proc_id: None,
@@ -159,9 +367,10 @@ impl<'a> Message<'a> {
 
         let ts_rem;
         let ts_attempt = parsers::iso8601_timestamp(rem);
-        if let Ok((timestamp, rem)) = ts_attempt {
+        if let Ok((timestamp, ts_end)) = ts_attempt {
             result.timestamp = Some(timestamp);
-            ts_rem = rem;
+            result.timestamp_text = consumed(rem, ts_end);
+            ts_rem = ts_end;
         } else {
             let err = ts_attempt.unwrap_err();
             let (_, nil_rem) = parsers::byte(rem, b'-').map_err(move |_| err)?;
@@ -205,29 +414,57 @@ impl<'a> Message<'a> {
                 is_utf8 = true;
             }
 
-            result.message = if is_utf8 {
-                let trimmed = std::str::from_utf8(message_bytes)?.trim();
-                if trimmed.len() > 0 {
-                    Some(Cow::Borrowed(trimmed))
-                } else {
-                    None
-                }
+            // A BOM declares the message as UTF-8, so invalid bytes are replaced rather than decoded with a fallback charset
+            let message = if is_utf8 {
+                String::from_utf8_lossy(message_bytes)
             } else {
-                let owned = String::from_utf8_lossy(message_bytes);
-                let trimmed = owned.trim();
-                if trimmed.len() > 0 {
-                    Some(Cow::Owned(trimmed.to_owned()))
-                } else {
-                    None
-                }
+                charsets.decode(message_bytes, result.app_name, result.hostname)
             };
+
+            result.message = match message {
+                Cow::Borrowed(message) => Some(Cow::Borrowed(trim_message(message, keep_indentation))),
+                Cow::Owned(message) => Some(Cow::Owned(trim_message(&message, keep_indentation).to_owned())),
+            }
+            .filter(|message| !message.is_empty());
         }
 
         Ok(result)
     }
 }
 
+// Whether the input starts with an RFC5424 `<PRIVAL>VERSION` header
+fn is_rfc5424_header(msg: &[u8]) -> bool {
+    match parsers::priority(msg) {
+        Ok((_, rem)) => rem.starts_with(b"1 "),
+        Err(_) => false,
+    }
+}
+
+// The text consumed by a parser, given the input before and after it
+fn consumed<'a>(before: &'a [u8], after: &[u8]) -> Option<&'a str> {
+    std::str::from_utf8(&before[..before.len() - after.len()]).ok()
+}
+
+fn trim_message(message: &str, keep_indentation: bool) -> &str {
+    if keep_indentation {
+        message.trim_end()
+    } else {
+        message.trim()
+    }
+}
+
+fn trim_bytes(mut msg: &[u8]) -> &[u8] {
+    while let Some((last, rest)) = msg.split_last() {
+        if !last.is_ascii_whitespace() {
+            break;
+        }
+        msg = rest;
+    }
+    msg
+}
+
 #[cfg(test)]
+#[allow(clippy::len_zero, clippy::vec_init_then_push)]
 mod tests {
     use super::*;
     use chrono::{Datelike, TimeZone};
@@ -256,6 +493,7 @@ mod tests {
                 severity: 6,
             },
             timestamp: to_timestamp("2020-02-13T00:51:39.527825Z"),
+            timestamp_text: Some("2020-02-13T00:51:39.527825Z"),
             hostname: Some("docker-desktop"),
             app_name: Some("8b1089798cf8"),
             proc_id: Some("1481"),
@@ -289,6 +527,7 @@ mod tests {
                 severity: 2,
             },
             timestamp: to_timestamp("2003-10-11T22:14:15.003Z"),
+            timestamp_text: Some("2003-10-11T22:14:15.003Z"),
             hostname: Some("mymachine.example.com"),
             app_name: Some("su"),
             proc_id: None,
@@ -302,6 +541,70 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn parse_rfc5424_invalid_utf8_after_bom_lossily() {
+        let input = b"<34>1 2003-10-11T22:14:15.003Z mymachine.example.com su - ID47 - \xEF\xBB\xBFcaf\xE9 closed\n";
+
+        let actual = Message::from_rfc5424_bytes(input).expect("could not parse input for syslog");
+
+        assert_eq!(Some("su"), actual.app_name);
+        assert_eq!(Some(Cow::Owned("caf\u{fffd} closed".to_owned())), actual.message);
+    }
+
+    #[test]
+    fn parse_rfc3164_tag() {
+        let cases: [(&[u8], Option<&str>, Option<&str>); 4] = [
+            (b"<34>Oct 11 22:14:15 mymachine su[42]: 'su root' failed", Some("su"), Some("42")),
+            (b"<34>Oct 11 22:14:15 mymachine kernel: eth0 up", Some("kernel"), None),
+            (b"<34>Oct 11 22:14:15 mymachine 'su root' failed", None, None),
+            (b"<34>Oct 11 22:14:15 mymachine su[42] failed", None, None),
+        ];
+
+        for (input, app_name, proc_id) in &cases {
+            let (actual, _) = Message::from_bytes_with_charsets(input, ParseMode::Auto, &Charsets::default(), false, true, &Utc::now());
+            let (strict, rejection) = Message::from_bytes_with_charsets(input, ParseMode::Strict3164, &Charsets::default(), false, true, &Utc::now());
+
+            assert_eq!(*app_name, actual.app_name);
+            assert_eq!(*proc_id, actual.proc_id);
+            assert_eq!(None, rejection);
+            assert_eq!(actual, strict);
+        }
+
+        let actual = Message::from_rfc3164_bytes(cases[0].0, &Utc::now());
+        assert_eq!(None, actual.app_name);
+        assert_eq!(None, actual.proc_id);
+        assert_eq!(Some("su[42]: 'su root' failed"), actual.message.as_deref());
+    }
+
+    #[test]
+    fn parse_rfc5424_keeping_indentation() {
+        let input = b"<27>1 2003-10-11T22:14:15.003Z host1 app 42 - - \tat com.example.Main.main(Main.java:7)\n";
+
+        let (trimmed, _) = Message::from_bytes_with_charsets(input, ParseMode::Auto, &Charsets::default(), false, false, &Utc::now());
+        let (indented, _) = Message::from_bytes_with_charsets(input, ParseMode::Auto, &Charsets::default(), true, false, &Utc::now());
+
+        assert_eq!(Some("at com.example.Main.main(Main.java:7)"), trimmed.message.as_deref());
+        assert_eq!(Some("\tat com.example.Main.main(Main.java:7)"), indented.message.as_deref());
+    }
+
+    #[test]
+    fn parse_with_fallback_charset() {
+        let charsets: Charsets = "windows-1252 [hostname=legacy-*]; windows-1252 [app_name=legacy]".parse().unwrap();
+
+        let cases: [(&[u8], &str); 4] = [
+            (b"<34>Oct 11 22:14:15 legacy-01 app: caf\xE9 closed", "app: caf\u{e9} closed"),
+            (b"<34>Oct 11 22:14:15 modern-01 legacy[42]: caf\xE9 closed", "legacy[42]: caf\u{e9} closed"),
+            (b"<34>1 2003-10-11T22:14:15.003Z legacy-01 app - - - caf\xE9 closed", "caf\u{e9} closed"),
+            (b"<34>1 2003-10-11T22:14:15.003Z modern-01 app - - - caf\xE9 closed", "caf\u{fffd} closed"),
+        ];
+
+        for (input, expected) in &cases {
+            let (msg, _) = Message::from_bytes_with_charsets(input, ParseMode::Auto, &charsets, false, false, &Utc::now());
+
+            assert_eq!(Some(*expected), msg.message.as_deref());
+        }
+    }
+
     #[test]
     fn parse_rfc5424_syslog_specs_example_2() {
         // example 2 from https://tools.ietf.org/html/rfc5424
@@ -313,6 +616,7 @@ mod tests {
                 severity: 5,
             },
             timestamp: to_timestamp("2003-08-24T05:14:15.000003-07:00"),
+            timestamp_text: Some("2003-08-24T05:14:15.000003-07:00"),
             hostname: Some("192.0.2.1"),
             app_name: Some("myproc"),
             proc_id: Some("8710"),
@@ -342,6 +646,7 @@ mod tests {
                 severity: 5,
             },
             timestamp: to_timestamp("2003-10-11T22:14:15.003Z"),
+            timestamp_text: Some("2003-10-11T22:14:15.003Z"),
             hostname: Some("mymachine.example.com"),
             app_name: Some("evntslog"),
             proc_id: None,
@@ -389,6 +694,7 @@ mod tests {
                 severity: 5,
             },
             timestamp: to_timestamp("2003-10-11T22:14:15.003Z"),
+            timestamp_text: Some("2003-10-11T22:14:15.003Z"),
             hostname: Some("mymachine.example.com"),
             app_name: Some("evntslog"),
             proc_id: None,
@@ -412,6 +718,7 @@ mod tests {
                 severity: 0,
             },
             timestamp: None,
+            timestamp_text: None,
             hostname: None,
             app_name: None,
             proc_id: None,
@@ -463,6 +770,99 @@ mod tests {
         assert_eq!(msg.message, Some(Borrowed("su: 'su root' failed for lonvick on /dev/pts/8")));
     }
 
+    #[test]
+    fn parse_rfc3164_timestamp_variants() {
+        let now = Utc.ymd(2023, 10, 11).and_hms(0, 0, 0);
+        let cases: &[&[u8]] = &[
+            b"<34>Oct 11 22:14:15.123456 mymachine su: 'su root' failed",
+            b"<34>Oct 11 2023 22:14:15 mymachine su: 'su root' failed",
+            b"<34>Oct 11 22:14:15 UTC mymachine su: 'su root' failed",
+        ];
+
+        for input in cases {
+            let msg = Message::from_rfc3164_bytes(input, &now);
+
+            assert_eq!(msg.hostname, Some("mymachine"));
+            assert_eq!(msg.message, Some(Borrowed("su: 'su root' failed")));
+        }
+    }
+
+    #[test]
+    fn auto_mode_falls_back_silently() {
+        let input = b"<165>1 2003-10-11T22:14:15.003Z mymachine evntslog - ID47 [exampleSDID@32473 iut=\"3] An event";
+
+        let (msg, rejection) = Message::from_bytes_with_mode(input, ParseMode::Auto, &Utc::now());
+
+        assert_eq!(None, rejection);
+        assert_eq!(None, msg.app_name);
+    }
+
+    #[test]
+    fn lenient_mode_reports_rfc5424_rejections() {
+        let input = b"<165>1 2003-10-11T22:14:15.003Z mymachine evntslog - ID47 [exampleSDID@32473 iut=\"3] An event";
+
+        let (msg, rejection) = Message::from_bytes_with_mode(input, ParseMode::Lenient, &Utc::now());
+
+        assert_eq!(Format::Rfc5424, rejection.expect("missing rejection").format);
+        assert_eq!(None, msg.app_name);
+        assert_eq!(msg.priority.facility, 20);
+    }
+
+    #[test]
+    fn lenient_mode_ignores_rfc3164_messages() {
+        let input = b"<34>Oct 11 22:14:15 mymachine su: 'su root' failed for lonvick on /dev/pts/8";
+
+        let (msg, rejection) = Message::from_bytes_with_mode(input, ParseMode::Lenient, &Utc::now());
+
+        assert_eq!(None, rejection);
+        assert_eq!(Some("mymachine"), msg.hostname);
+    }
+
+    #[test]
+    fn strict_5424_mode_rejects_rfc3164_messages() {
+        let input = b"<34>Oct 11 22:14:15 mymachine su: 'su root' failed\n";
+
+        let (msg, rejection) = Message::from_bytes_with_mode(input, ParseMode::Strict5424, &Utc::now());
+
+        let rejection = rejection.expect("missing rejection");
+        assert_eq!(Format::Rfc5424, rejection.format);
+        assert_eq!("invalid message, version not 1", rejection.reason);
+        assert_eq!(None, msg.hostname);
+        assert_eq!(Some(Borrowed("<34>Oct 11 22:14:15 mymachine su: 'su root' failed")), msg.message);
+    }
+
+    #[test]
+    fn strict_5424_mode_accepts_rfc5424_messages() {
+        let input = b"<165>1 2003-08-24T05:14:15.000003-07:00 192.0.2.1 myproc 8710 - - %% It's time to make the do-nuts.\n";
+
+        let (msg, rejection) = Message::from_bytes_with_mode(input, ParseMode::Strict5424, &Utc::now());
+
+        assert_eq!(None, rejection);
+        assert_eq!(Some("myproc"), msg.app_name);
+    }
+
+    #[test]
+    fn strict_3164_mode_requires_hostname() {
+        let input = b"<34>Oct 11 22:14:15 ";
+
+        let (_, rejection) = Message::from_bytes_with_mode(input, ParseMode::Strict3164, &Utc::now());
+
+        let rejection = rejection.expect("missing rejection");
+        assert_eq!(Format::Rfc3164, rejection.format);
+        assert_eq!("missing hostname", rejection.reason);
+    }
+
+    #[test]
+    fn strict_3164_mode_accepts_rfc3164_messages() {
+        let input = b"<34>Oct 11 22:14:15 mymachine su: 'su root' failed";
+
+        let (msg, rejection) = Message::from_bytes_with_mode(input, ParseMode::Strict3164, &Utc::now());
+
+        assert_eq!(None, rejection);
+        assert_eq!(Some("mymachine"), msg.hostname);
+        assert_eq!(Some(Borrowed("su: 'su root' failed")), msg.message);
+    }
+
     #[test]
     fn parse_rfc3164_example_1() {
         let input = b"Use the BFG!";
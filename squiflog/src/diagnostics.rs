@@ -39,7 +39,7 @@ pub struct Config {
 impl Default for Config {
     fn default() -> Self {
         Config {
-            metrics_interval_ms: 1 * 1000 * 60, // 1 minute
+            metrics_interval_ms: 1000 * 60, // 1 minute
             min_level: Level::Error,
         }
     }
@@ -196,7 +196,7 @@ impl<'a> DiagnosticEvent<'a> {
 
 pub fn emit(message_template: &'static str) {
     if MIN_LEVEL.includes(Level::Debug) {
-        let evt = DiagnosticEvent::new("DEBUG", None, &message_template, None);
+        let evt = DiagnosticEvent::new("DEBUG", None, message_template, None);
         let json = serde_json::to_string(&evt).expect("infallible JSON");
         eprintln!("{}", json);
     }
@@ -205,7 +205,7 @@ pub fn emit(message_template: &'static str) {
 pub fn emit_err(error: &impl Display, message_template: &'static str) {
     if MIN_LEVEL.includes(Level::Error) {
         let err_str = format!("{}", error);
-        let evt = DiagnosticEvent::new("ERROR", Some(&err_str), &message_template, None);
+        let evt = DiagnosticEvent::new("ERROR", Some(&err_str), message_template, None);
         let json = serde_json::to_string(&evt).expect("infallible JSON");
         eprintln!("{}", json);
     }
@@ -217,11 +217,15 @@ fn emit_metrics() {
         struct EmitMetrics {
             data: HashMap<&'static str, usize>,
             server: HashMap<&'static str, usize>,
+            clock_skew: HashMap<String, crate::data::skew::Stats>,
+            dropped_by_rule: HashMap<String, usize>,
         }
 
         let mut metrics = EmitMetrics {
             data: HashMap::new(),
             server: HashMap::new(),
+            clock_skew: crate::data::skew::take(),
+            dropped_by_rule: crate::data::filter::take(),
         };
 
         let data = METRICS.data.take();
@@ -18,9 +18,10 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
     diagnostics::init(config.diagnostics);
 
     // The processor for converting SYSLOG into CLEF
+    let data = data::build(config.data)?;
     let process = {
-        let data = data::build(config.data);
-        move |msg: Bytes| data.read_as_clef(&*msg)
+        let data = data.clone();
+        move |msg: Bytes| data.read_as_clef(&msg)
     };
 
     // The server that drives the receiver and processor
@@ -37,6 +38,7 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
 
     // Run the server and wait for it to exit
     server.run()?;
+    data.flush()?;
     diagnostics::stop()?;
 
     Ok(())
@@ -64,7 +66,7 @@ fn listen_for_stdin_closed(handle: server::Handle) {
 }
 
 fn main() {
-    let run_server: Result<(), Box<dyn std::error::Error>> = catch_unwind(|| run())
+    let run_server: Result<(), Box<dyn std::error::Error>> = catch_unwind(run)
         .map_err(|panic| unwrap_panic(panic).into())
         .and_then(|inner| inner);
 